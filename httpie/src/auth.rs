@@ -0,0 +1,321 @@
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use md5::{Digest, Md5};
+use reqwest::{header, Client, Request, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// --auth-type 支持的认证方式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthType {
+    Basic,
+    Bearer,
+    Digest,
+}
+
+/// --auth 传入的凭证，basic / digest 为 user:pass，bearer 为 token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Auth {
+    #[serde(rename = "type")]
+    pub auth_type: AuthType,
+    pub user: String,
+    pub password: String,
+}
+
+impl Auth {
+    /// 解析 --auth 的值，省略密码时在终端提示输入
+    pub fn new(auth_type: AuthType, credentials: &str) -> Result<Self> {
+        let (user, password) = match (auth_type, credentials.split_once(':')) {
+            (AuthType::Bearer, _) => (credentials.to_string(), String::new()),
+            (_, Some((user, password))) => (user.to_string(), password.to_string()),
+            (_, None) => {
+                let prompt = format!("http: password for {}: ", credentials);
+                (credentials.to_string(), rpassword::prompt_password(prompt)?)
+            }
+        };
+        Ok(Self {
+            auth_type,
+            user,
+            password,
+        })
+    }
+
+    /// basic / bearer 在发送前直接加上 Authorization 头，digest 需要等服务器的 challenge
+    pub fn apply(&self, req: RequestBuilder) -> RequestBuilder {
+        match self.auth_type {
+            AuthType::Basic => req.basic_auth(&self.user, Some(&self.password)),
+            AuthType::Bearer => req.bearer_auth(&self.user),
+            AuthType::Digest => req,
+        }
+    }
+
+    /// 发送请求，如果是 digest 认证且服务器返回 401 challenge，则带上 response 重新发送一次
+    pub async fn execute(&self, client: &Client, req: Request) -> Result<Response> {
+        let retry = match self.auth_type {
+            AuthType::Digest => req.try_clone(),
+            _ => None,
+        };
+        let resp = client.execute(req).await?;
+        let Some(mut retry) = retry else {
+            return Ok(resp);
+        };
+        if resp.status() != StatusCode::UNAUTHORIZED {
+            return Ok(resp);
+        }
+        let challenge = match resp
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(DigestChallenge::parse)
+        {
+            Some(challenge) => challenge,
+            None => return Ok(resp),
+        };
+
+        let url = retry.url();
+        let uri = match url.query() {
+            Some(q) => format!("{}?{}", url.path(), q),
+            None => url.path().to_string(),
+        };
+        let authorization = challenge.authorization(
+            &self.user,
+            &self.password,
+            retry.method().as_str(),
+            &uri,
+            &cnonce(),
+        )?;
+        retry
+            .headers_mut()
+            .insert(header::AUTHORIZATION, authorization.parse()?);
+        Ok(client.execute(retry).await?)
+    }
+}
+
+/// 服务器在 WWW-Authenticate 中返回的 digest challenge
+#[derive(Debug, Clone, PartialEq)]
+struct DigestChallenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    qop: Option<String>,
+    algorithm: Option<String>,
+}
+
+impl DigestChallenge {
+    /// 解析 `Digest realm="...", nonce="...", qop="auth", ...`
+    fn parse(s: &str) -> Option<Self> {
+        let (scheme, params) = s.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("digest") {
+            return None;
+        }
+        let mut params = parse_params(params);
+        Some(Self {
+            realm: params.remove("realm")?,
+            nonce: params.remove("nonce")?,
+            opaque: params.remove("opaque"),
+            qop: params.remove("qop"),
+            algorithm: params.remove("algorithm"),
+        })
+    }
+
+    /// 按照 RFC 2617 计算 Authorization 头
+    fn authorization(
+        &self,
+        user: &str,
+        password: &str,
+        method: &str,
+        uri: &str,
+        cnonce: &str,
+    ) -> Result<String> {
+        let algorithm = self.algorithm.as_deref().unwrap_or("MD5");
+        let mut ha1 = md5_hex(&format!("{}:{}:{}", user, self.realm, password));
+        match algorithm.to_ascii_uppercase().as_str() {
+            "MD5" => {}
+            "MD5-SESS" => ha1 = md5_hex(&format!("{}:{}:{}", ha1, self.nonce, cnonce)),
+            _ => return Err(anyhow!("Unsupported digest algorithm {}", algorithm)),
+        }
+        let ha2 = md5_hex(&format!("{}:{}", method, uri));
+
+        // 服务器可能同时支持 auth 和 auth-int，我们只实现了 auth
+        let qop = self
+            .qop
+            .as_ref()
+            .map(|qop| qop.split(',').map(str::trim).any(|q| q == "auth"));
+        let mut auth = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\"",
+            user, self.realm, self.nonce, uri
+        );
+        match qop {
+            Some(true) => {
+                let nc = "00000001";
+                let response = md5_hex(&format!(
+                    "{}:{}:{}:{}:auth:{}",
+                    ha1, self.nonce, nc, cnonce, ha2
+                ));
+                auth.push_str(&format!(
+                    ", qop=auth, nc={}, cnonce=\"{}\", response=\"{}\"",
+                    nc, cnonce, response
+                ));
+            }
+            Some(false) => return Err(anyhow!("Unsupported digest qop {:?}", self.qop)),
+            None => {
+                let response = md5_hex(&format!("{}:{}:{}", ha1, self.nonce, ha2));
+                auth.push_str(&format!(", response=\"{}\"", response));
+            }
+        }
+        if let Some(opaque) = &self.opaque {
+            auth.push_str(&format!(", opaque=\"{}\"", opaque));
+        }
+        if self.algorithm.is_some() {
+            auth.push_str(&format!(", algorithm={}", algorithm));
+        }
+        Ok(auth)
+    }
+}
+
+/// 解析逗号分隔的 key=value / key="value" 参数
+fn parse_params(s: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    let mut rest = s.trim();
+    while let Some((k, v)) = rest.split_once('=') {
+        let k = k.trim().trim_start_matches(',').trim().to_ascii_lowercase();
+        let v = v.trim_start();
+        let (value, remain) = match v.strip_prefix('"') {
+            Some(v) => {
+                let end = v.find('"').unwrap_or(v.len());
+                (&v[..end], v.get(end + 1..).unwrap_or(""))
+            }
+            None => {
+                let end = v.find(',').unwrap_or(v.len());
+                (v[..end].trim(), &v[end..])
+            }
+        };
+        params.insert(k, value.to_string());
+        rest = remain.trim_start().trim_start_matches(',');
+    }
+    params
+}
+
+fn md5_hex(s: &str) -> String {
+    format!("{:x}", Md5::digest(s.as_bytes()))
+}
+
+fn cnonce() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    md5_hex(&nanos.to_string())[..16].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[test]
+    fn auth_should_split_credentials() {
+        let auth = Auth::new(AuthType::Basic, "user:p:ss").unwrap();
+        assert_eq!(auth.user, "user");
+        assert_eq!(auth.password, "p:ss");
+
+        let auth = Auth::new(AuthType::Bearer, "abc:def").unwrap();
+        assert_eq!(auth.user, "abc:def");
+    }
+
+    #[test]
+    fn digest_challenge_should_parse() {
+        let challenge = DigestChallenge::parse(
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+        )
+        .unwrap();
+        assert_eq!(challenge.realm, "testrealm@host.com");
+        assert_eq!(challenge.qop.as_deref(), Some("auth,auth-int"));
+        assert_eq!(challenge.nonce, "dcd98b7102dd2f0e8b11d0f600bfb0c093");
+        assert_eq!(
+            challenge.opaque.as_deref(),
+            Some("5ccc069c403ebaf9f0171e9517f40e41")
+        );
+        assert!(DigestChallenge::parse(r#"Basic realm="x""#).is_none());
+    }
+
+    #[test]
+    fn digest_response_should_match_rfc2617_example() {
+        let challenge = DigestChallenge {
+            realm: "testrealm@host.com".into(),
+            nonce: "dcd98b7102dd2f0e8b11d0f600bfb0c093".into(),
+            opaque: Some("5ccc069c403ebaf9f0171e9517f40e41".into()),
+            qop: Some("auth,auth-int".into()),
+            algorithm: None,
+        };
+        let auth = challenge
+            .authorization(
+                "Mufasa",
+                "Circle Of Life",
+                "GET",
+                "/dir/index.html",
+                "0a4f113b",
+            )
+            .unwrap();
+        assert!(auth.contains(r#"response="6629fae49393a05397450978507c4ef1""#));
+        assert!(auth.contains(r#"opaque="5ccc069c403ebaf9f0171e9517f40e41""#));
+    }
+
+    /// 一个只处理两次请求的 digest stub server：第一次返回 challenge，第二次校验 Authorization
+    async fn digest_stub_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                let req = String::from_utf8_lossy(&buf[..n]).to_string();
+                let authorization = req
+                    .lines()
+                    .find_map(|l| l.strip_prefix("authorization: "))
+                    .map(|s| s.to_string());
+                let resp = match authorization {
+                    Some(auth) => {
+                        let params = parse_params(auth.trim_start_matches("Digest "));
+                        let challenge = DigestChallenge {
+                            realm: "stub".into(),
+                            nonce: "n0nce".into(),
+                            opaque: None,
+                            qop: Some("auth".into()),
+                            algorithm: None,
+                        };
+                        let expected = challenge
+                            .authorization("tyr", "secret", "GET", "/a?b=1", &params["cnonce"])
+                            .unwrap();
+                        match expected == auth {
+                            true => "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok",
+                            false => "HTTP/1.1 403 Forbidden\r\ncontent-length: 0\r\n\r\n",
+                        }
+                    }
+                    None => "HTTP/1.1 401 Unauthorized\r\nwww-authenticate: Digest realm=\"stub\", nonce=\"n0nce\", qop=\"auth\"\r\ncontent-length: 0\r\n\r\n",
+                };
+                stream.write_all(resp.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://{}/a?b=1", addr)
+    }
+
+    #[tokio::test]
+    async fn digest_auth_should_answer_challenge() {
+        let url = digest_stub_server().await;
+        let client = Client::new();
+        let auth = Auth::new(AuthType::Digest, "tyr:secret").unwrap();
+        let req = auth.apply(client.get(&url)).build().unwrap();
+        let resp = auth.execute(&client, req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.text().await.unwrap(), "ok");
+    }
+}
@@ -0,0 +1,21 @@
+use std::{
+    env,
+    process::{self, Command},
+};
+
+/// `https` 是 httpie 的别名，区别只是 URL 没有 scheme 时默认使用 https
+fn main() {
+    let exe = env::current_exe()
+        .map(|exe| exe.with_file_name(format!("httpie{}", env::consts::EXE_SUFFIX)))
+        .unwrap_or_else(|_| "httpie".into());
+    // 放在用户参数之前，这样命令行中的 --default-scheme 仍然可以覆盖它
+    let status = Command::new(&exe)
+        .arg("--default-scheme=https")
+        .args(env::args_os().skip(1))
+        .status()
+        .unwrap_or_else(|e| {
+            eprintln!("Error: failed to run {}: {}", exe.display(), e);
+            process::exit(1);
+        });
+    process::exit(status.code().unwrap_or(1));
+}
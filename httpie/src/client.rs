@@ -0,0 +1,454 @@
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, ValueEnum};
+use native_tls::{Protocol, TlsConnector};
+use openssl::{pkey::PKey, x509::X509};
+use reqwest::{tls, Certificate, ClientBuilder, Identity, NoProxy, Proxy, Version};
+use std::{fs, path::PathBuf, str::FromStr};
+
+/// TLS 和代理相关的命令行参数，最终都作用在 reqwest 的 ClientBuilder 上
+#[derive(Args, Debug, Clone)]
+pub struct ConnectionArgs {
+    /// Verify the server certificate: yes, no, or the path of a CA bundle (PEM)
+    /// to trust instead of the system roots
+    #[arg(long, global = true, default_value = "yes", value_parser = parse_verify)]
+    pub verify: Verify,
+
+    /// Client certificate (PEM) for mutual TLS. May also contain the private key
+    #[arg(long, global = true)]
+    pub cert: Option<PathBuf>,
+
+    /// Private key (PEM) of --cert, if it is not included in the certificate file
+    #[arg(long, global = true, requires = "cert")]
+    pub cert_key: Option<PathBuf>,
+
+    /// Minimum TLS version to accept
+    #[arg(long, global = true, value_enum)]
+    pub ssl: Option<SslVersion>,
+
+    /// Proxy for a URL scheme, e.g. http:http://proxy:3128, https:http://proxy:3128
+    /// or all:http://proxy:3128. Hosts in NO_PROXY are always connected directly
+    #[arg(long, global = true, value_parser = parse_proxy)]
+    pub proxy: Vec<ProxySpec>,
+
+    /// HTTP version to speak. 2 uses prior knowledge, so plain http:// URLs
+    /// are sent over h2c. By default HTTP/2 is used when negotiated via TLS ALPN
+    #[arg(long, global = true, value_enum)]
+    pub http_version: Option<HttpVersion>,
+}
+
+/// --verify 的取值
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verify {
+    Yes,
+    No,
+    Bundle(PathBuf),
+}
+
+impl FromStr for Verify {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "yes" | "true" => Verify::Yes,
+            "no" | "false" => Verify::No,
+            _ => Verify::Bundle(s.into()),
+        })
+    }
+}
+
+fn parse_verify(s: &str) -> Result<Verify> {
+    s.parse()
+}
+
+/// --ssl 支持的最低 TLS 版本
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum SslVersion {
+    #[value(name = "tls1")]
+    Tls1,
+    #[value(name = "tls1.1")]
+    Tls1_1,
+    #[value(name = "tls1.2")]
+    Tls1_2,
+    #[value(name = "tls1.3")]
+    Tls1_3,
+}
+
+impl From<SslVersion> for tls::Version {
+    fn from(v: SslVersion) -> Self {
+        match v {
+            SslVersion::Tls1 => tls::Version::TLS_1_0,
+            SslVersion::Tls1_1 => tls::Version::TLS_1_1,
+            SslVersion::Tls1_2 => tls::Version::TLS_1_2,
+            SslVersion::Tls1_3 => tls::Version::TLS_1_3,
+        }
+    }
+}
+
+/// --http-version 的取值。HTTP/3 在 reqwest 中还是不稳定的特性，等稳定后再加到这里
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum HttpVersion {
+    #[value(name = "1.0")]
+    Http1_0,
+    #[value(name = "1.1")]
+    Http1_1,
+    #[value(name = "2")]
+    Http2,
+}
+
+impl From<HttpVersion> for Version {
+    fn from(v: HttpVersion) -> Self {
+        match v {
+            HttpVersion::Http1_0 => Version::HTTP_10,
+            HttpVersion::Http1_1 => Version::HTTP_11,
+            HttpVersion::Http2 => Version::HTTP_2,
+        }
+    }
+}
+
+/// --proxy 的取值，和 httpie 一样写成 `scheme:proxy_url`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxySpec {
+    pub scheme: String,
+    pub url: String,
+}
+
+impl FromStr for ProxySpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scheme, url) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid proxy {}, expect scheme:url", s))?;
+        let scheme = scheme.to_ascii_lowercase();
+        if !matches!(scheme.as_str(), "http" | "https" | "all") {
+            return Err(anyhow!(
+                "Invalid proxy scheme {}, expect http, https or all",
+                scheme
+            ));
+        }
+        Ok(Self {
+            scheme,
+            url: url.to_string(),
+        })
+    }
+}
+
+fn parse_proxy(s: &str) -> Result<ProxySpec> {
+    s.parse()
+}
+
+impl ConnectionArgs {
+    /// --http-version 指定的请求版本
+    pub fn version(&self) -> Option<Version> {
+        self.http_version.map(Version::from)
+    }
+
+    /// 把 TLS、代理以及 HTTP 版本的配置应用到 ClientBuilder 上
+    pub fn apply(&self, mut builder: ClientBuilder) -> Result<ClientBuilder> {
+        builder = match &self.verify {
+            Verify::Yes => builder,
+            Verify::No => builder.danger_accept_invalid_certs(true),
+            Verify::Bundle(path) => {
+                let pem = read(path)?;
+                let certs = Certificate::from_pem_bundle(&pem)
+                    .with_context(|| format!("Invalid CA bundle {}", path.display()))?;
+                certs
+                    .into_iter()
+                    .fold(builder.tls_built_in_root_certs(false), |b, cert| {
+                        b.add_root_certificate(cert)
+                    })
+            }
+        };
+
+        if let Some((cert, cert_pem, key_pem)) = self.client_cert()? {
+            let identity = Identity::from_pkcs8_pem(&cert_pem, &key_pem)
+                .with_context(|| format!("Invalid client certificate {}", cert.display()))?;
+            builder = builder.identity(identity);
+        }
+
+        if let Some(version) = self.ssl {
+            builder = builder.min_tls_version(version.into());
+        }
+
+        // 请求本身的版本由调用者设置，这里只限定连接使用的协议
+        builder = match self.http_version {
+            Some(HttpVersion::Http1_0 | HttpVersion::Http1_1) => builder.http1_only(),
+            Some(HttpVersion::Http2) => builder.http2_prior_knowledge(),
+            None => builder,
+        };
+
+        for spec in &self.proxy {
+            let proxy = match spec.scheme.as_str() {
+                "http" => Proxy::http(&spec.url)?,
+                "https" => Proxy::https(&spec.url)?,
+                _ => Proxy::all(&spec.url)?,
+            };
+            builder = builder.proxy(proxy.no_proxy(NoProxy::from_env()));
+        }
+        Ok(builder)
+    }
+
+    /// WebSocket 连接不经过 reqwest，这里按同样的 --verify、--cert 和 --ssl 构造 TLS 连接器
+    pub fn tls_connector(&self) -> Result<TlsConnector> {
+        let mut builder = TlsConnector::builder();
+        match &self.verify {
+            Verify::Yes => {}
+            Verify::No => {
+                builder.danger_accept_invalid_certs(true);
+            }
+            Verify::Bundle(path) => {
+                let certs = X509::stack_from_pem(&read(path)?)
+                    .with_context(|| format!("Invalid CA bundle {}", path.display()))?;
+                builder.disable_built_in_roots(true);
+                for cert in certs {
+                    builder
+                        .add_root_certificate(native_tls::Certificate::from_der(&cert.to_der()?)?);
+                }
+            }
+        }
+        if let Some((cert, cert_pem, key_pem)) = self.client_cert()? {
+            let identity = native_tls::Identity::from_pkcs8(&cert_pem, &key_pem)
+                .with_context(|| format!("Invalid client certificate {}", cert.display()))?;
+            builder.identity(identity);
+        }
+        // native-tls 不支持指定 TLS 1.3 为最低版本，不能悄悄放宽成 TLS 1.2
+        let min = match self.ssl {
+            Some(SslVersion::Tls1) => Some(Protocol::Tlsv10),
+            Some(SslVersion::Tls1_1) => Some(Protocol::Tlsv11),
+            Some(SslVersion::Tls1_2) => Some(Protocol::Tlsv12),
+            Some(SslVersion::Tls1_3) => {
+                return Err(anyhow!(
+                    "--ssl=tls1.3 is not supported for WebSocket connections"
+                ))
+            }
+            None => None,
+        };
+        builder.min_protocol_version(min);
+        Ok(builder.build()?)
+    }
+
+    /// --cert 和 --cert-key 的内容。证书文件中已经包含私钥时，直接从同一个文件中读取私钥。
+    /// reqwest 和 native-tls 都只接受 PKCS#8 格式的私钥，PKCS#1 和 SEC1 的私钥需要先转换
+    fn client_cert(&self) -> Result<Option<ClientCert<'_>>> {
+        let Some(cert) = &self.cert else {
+            return Ok(None);
+        };
+        let cert_pem = read(cert)?;
+        let key_path = self.cert_key.as_ref().unwrap_or(cert);
+        let key_pem = match &self.cert_key {
+            Some(key) => read(key)?,
+            None => cert_pem.clone(),
+        };
+        let key_pem = PKey::private_key_from_pem(&key_pem)
+            .and_then(|key| key.private_key_to_pem_pkcs8())
+            .with_context(|| format!("Invalid private key in {}", key_path.display()))?;
+        Ok(Some((cert, cert_pem, key_pem)))
+    }
+}
+
+/// --cert 的路径，以及证书和私钥的 PEM 内容
+type ClientCert<'a> = (&'a PathBuf, Vec<u8>, Vec<u8>);
+
+fn read(path: &PathBuf) -> Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::{
+        ec::EcKey,
+        ssl::{SslAcceptor, SslMethod, SslVerifyMode},
+    };
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+    use reqwest::Client;
+    use std::{io::Write, net::TcpListener, thread};
+
+    fn args(verify: &str) -> ConnectionArgs {
+        ConnectionArgs {
+            verify: verify.parse().unwrap(),
+            cert: None,
+            cert_key: None,
+            ssl: None,
+            proxy: vec![],
+            http_version: None,
+        }
+    }
+
+    /// 生成本地 CA 以及由它签发的 localhost 证书，启动一个只返回 ok 的 TLS server。
+    /// client_auth 为 true 时 server 要求客户端提供同一个 CA 签发的证书，
+    /// 返回值中的第三项是包含客户端证书和 SEC1 格式私钥的文件
+    fn tls_server(client_auth: bool) -> (String, PathBuf, PathBuf) {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        // CA 和 localhost 证书的 subject 不能相同，否则 openssl 会把后者当成自签名证书
+        ca_params
+            .distinguished_name
+            .push(DnType::CommonName, "httpie test CA");
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .signed_by(&key, &ca, &ca_key)
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let ca_path = std::env::temp_dir().join(format!("httpie-ca-{}.pem", port));
+        fs::write(&ca_path, ca.pem()).unwrap();
+
+        let client_key = KeyPair::generate().unwrap();
+        let client_cert = CertificateParams::new(Vec::<String>::new())
+            .unwrap()
+            .signed_by(&client_key, &ca, &ca_key)
+            .unwrap();
+        let sec1 = EcKey::private_key_from_pem(client_key.serialize_pem().as_bytes())
+            .unwrap()
+            .private_key_to_pem()
+            .unwrap();
+        let client_path = std::env::temp_dir().join(format!("httpie-client-{}.pem", port));
+        fs::write(&client_path, [client_cert.pem().as_bytes(), &sec1].concat()).unwrap();
+
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        acceptor
+            .set_private_key(&PKey::private_key_from_pem(key.serialize_pem().as_bytes()).unwrap())
+            .unwrap();
+        acceptor
+            .set_certificate(&X509::from_pem(cert.pem().as_bytes()).unwrap())
+            .unwrap();
+        if client_auth {
+            acceptor
+                .cert_store_mut()
+                .add_cert(X509::from_pem(ca.pem().as_bytes()).unwrap())
+                .unwrap();
+            acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        }
+        let acceptor = acceptor.build();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // 证书校验失败时握手会出错，忽略即可
+                if let Ok(mut stream) = acceptor.accept(stream) {
+                    let mut buf = [0; 4096];
+                    let _ = std::io::Read::read(&mut stream, &mut buf);
+                    let _ = stream.write_all(
+                        b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok",
+                    );
+                }
+            }
+        });
+        (format!("https://localhost:{}/", port), ca_path, client_path)
+    }
+
+    async fn get(args: &ConnectionArgs, url: &str) -> Result<String> {
+        let client = args.apply(Client::builder())?.build()?;
+        Ok(client.get(url).send().await?.text().await?)
+    }
+
+    #[tokio::test]
+    async fn verify_should_use_ca_bundle() {
+        let (url, ca_path, client_path) = tls_server(false);
+        assert!(get(&args("yes"), &url).await.is_err());
+        assert_eq!(
+            get(&args(ca_path.to_str().unwrap()), &url).await.unwrap(),
+            "ok"
+        );
+        assert_eq!(get(&args("no"), &url).await.unwrap(), "ok");
+        fs::remove_file(ca_path).unwrap();
+        fs::remove_file(client_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cert_should_authenticate_client() {
+        let (url, ca_path, client_path) = tls_server(true);
+        let args = ConnectionArgs {
+            cert: Some(client_path.clone()),
+            ..args(ca_path.to_str().unwrap())
+        };
+        assert_eq!(get(&args, &url).await.unwrap(), "ok");
+        assert!(get(&self::args("no"), &url).await.is_err());
+
+        // ws 使用的 native-tls 连接器也要带上同一个客户端证书
+        let addr = url.trim_start_matches("https://").trim_end_matches('/');
+        let stream = std::net::TcpStream::connect(addr).unwrap();
+        let mut stream = args
+            .tls_connector()
+            .unwrap()
+            .connect("localhost", stream)
+            .unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut resp = String::new();
+        std::io::Read::read_to_string(&mut stream, &mut resp).unwrap();
+        assert!(resp.ends_with("ok"));
+        fs::remove_file(ca_path).unwrap();
+        fs::remove_file(client_path).unwrap();
+    }
+
+    /// 返回客户端按 --http-version 发出的第一行：HTTP/1.x 的请求行或者 HTTP/2 的连接前言
+    async fn first_line(version: HttpVersion) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://127.0.0.1:{}/",
+            listener.local_addr().unwrap().port()
+        );
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 4096];
+            let n = std::io::Read::read(&mut stream, &mut buf).unwrap();
+            String::from_utf8_lossy(&buf[..n])
+                .lines()
+                .next()
+                .unwrap()
+                .to_string()
+        });
+        let args = ConnectionArgs {
+            http_version: Some(version),
+            ..args("yes")
+        };
+        let client = args.apply(Client::builder()).unwrap().build().unwrap();
+        let req = client.get(&url).version(args.version().unwrap());
+        // 服务端不会正常响应，这里只关心客户端发出了什么
+        let _ = tokio::time::timeout(std::time::Duration::from_secs(2), req.send()).await;
+        server.join().unwrap()
+    }
+
+    #[tokio::test]
+    async fn http_version_should_select_protocol() {
+        assert_eq!(first_line(HttpVersion::Http1_0).await, "GET / HTTP/1.0");
+        assert_eq!(first_line(HttpVersion::Http1_1).await, "GET / HTTP/1.1");
+        assert_eq!(first_line(HttpVersion::Http2).await, "PRI * HTTP/2.0");
+    }
+
+    #[test]
+    fn tls_connector_should_reject_tls13_minimum() {
+        let mut args = args("yes");
+        args.ssl = Some(SslVersion::Tls1_2);
+        assert!(args.tls_connector().is_ok());
+        args.ssl = Some(SslVersion::Tls1_3);
+        assert!(args.tls_connector().is_err());
+    }
+
+    #[test]
+    fn parse_verify_should_work() {
+        assert_eq!(parse_verify("no").unwrap(), Verify::No);
+        assert_eq!(parse_verify("YES").unwrap(), Verify::Yes);
+        assert_eq!(
+            parse_verify("/etc/ca.pem").unwrap(),
+            Verify::Bundle("/etc/ca.pem".into())
+        );
+    }
+
+    #[test]
+    fn parse_proxy_should_work() {
+        assert_eq!(
+            parse_proxy("http:http://127.0.0.1:3128").unwrap(),
+            ProxySpec {
+                scheme: "http".into(),
+                url: "http://127.0.0.1:3128".into()
+            }
+        );
+        assert!(parse_proxy("ftp:http://127.0.0.1:3128").is_err());
+        assert!(parse_proxy("http").is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::auth::{Auth, AuthType};
+use anyhow::{anyhow, Context, Result};
+use reqwest::{
+    header::{self, HeaderMap},
+    Url,
+};
+use serde::Deserialize;
+use std::{collections::BTreeMap, ffi::OsString, fs, path::PathBuf};
+
+/// 配置文件 `<config dir>/httpie-rs/config.toml`，例如：
+///
+/// ```toml
+/// default_options = ["--follow", "--pretty=format"]
+///
+/// [hosts."api.example.com"]
+/// headers = { X-Api-Version = "2" }
+/// auth = "token"
+/// auth_type = "bearer"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// 每次调用都会带上的选项，放在命令行参数之前，命令行中再次指定时以命令行为准
+    default_options: Vec<String>,
+    /// 以 host 或 host:port 为 key 的默认值
+    hosts: BTreeMap<String, HostConfig>,
+}
+
+/// 请求的 URL 匹配某个 host 时注入的请求头和认证信息
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct HostConfig {
+    headers: BTreeMap<String, String>,
+    auth: Option<String>,
+    auth_type: Option<AuthType>,
+}
+
+impl Config {
+    /// 加载配置文件，文件不存在时使用空配置
+    pub fn load() -> Result<Self> {
+        let Some(path) = config_path() else {
+            return Ok(Self::default());
+        };
+        match path.exists() {
+            true => {
+                let content = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read config {}", path.display()))?;
+                content
+                    .parse()
+                    .with_context(|| format!("Invalid config file {}", path.display()))
+            }
+            false => Ok(Self::default()),
+        }
+    }
+
+    /// 把默认选项插到程序名之后，交给 clap 和命令行参数一起解析
+    pub fn args(&self, args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+        let mut args = args.into_iter();
+        args.next()
+            .into_iter()
+            .chain(self.default_options.iter().map(OsString::from))
+            .chain(args)
+            .collect()
+    }
+
+    /// 找到 URL 对应的 host 配置，host:port 比单独的 host 优先
+    pub fn host(&self, url: &str) -> Option<&HostConfig> {
+        let url: Url = url.parse().ok()?;
+        let host = url.host_str()?;
+        url.port()
+            .and_then(|port| self.hosts.get(&format!("{}:{}", host, port)))
+            .or_else(|| self.hosts.get(host))
+    }
+}
+
+impl std::str::FromStr for Config {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(toml::from_str(s)?)
+    }
+}
+
+impl HostConfig {
+    pub fn headers(&self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        for (k, v) in &self.headers {
+            headers.insert(header::HeaderName::from_bytes(k.as_bytes())?, v.parse()?);
+        }
+        Ok(headers)
+    }
+
+    /// auth_type 没有配置时使用命令行中的 --auth-type
+    pub fn auth(&self, auth_type: AuthType) -> Result<Option<Auth>> {
+        self.auth
+            .as_deref()
+            .map(|credentials| Auth::new(self.auth_type.unwrap_or(auth_type), credentials))
+            .transpose()
+            .map_err(|e| anyhow!("Invalid auth in config file: {}", e))
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("httpie-rs").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+default_options = ["--follow", "--pretty=format"]
+
+[hosts."example.com"]
+headers = { X-Api-Version = "2" }
+auth = "token"
+auth_type = "bearer"
+
+[hosts."example.com:8080"]
+auth = "user:pass"
+"#;
+
+    #[test]
+    fn args_should_insert_default_options() {
+        let config: Config = CONFIG.parse().unwrap();
+        let args = config.args(["http", "get", "example.com"].map(OsString::from));
+        assert_eq!(
+            args,
+            ["http", "--follow", "--pretty=format", "get", "example.com"].map(OsString::from)
+        );
+    }
+
+    #[test]
+    fn host_should_prefer_port() {
+        let config: Config = CONFIG.parse().unwrap();
+        let host = config.host("https://example.com/a").unwrap();
+        assert_eq!(host.headers().unwrap()["x-api-version"], "2");
+        let auth = host.auth(AuthType::Basic).unwrap().unwrap();
+        assert_eq!(auth.auth_type, AuthType::Bearer);
+
+        let host = config.host("http://example.com:8080/").unwrap();
+        assert!(host.headers().unwrap().is_empty());
+        let auth = host.auth(AuthType::Basic).unwrap().unwrap();
+        assert_eq!(auth.auth_type, AuthType::Basic);
+        assert_eq!(auth.password, "pass");
+
+        assert!(config.host("http://other.com/").is_none());
+    }
+}
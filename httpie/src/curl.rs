@@ -0,0 +1,507 @@
+use crate::{
+    auth::{Auth, AuthType},
+    client::Verify,
+};
+use anyhow::{anyhow, Context, Result};
+use reqwest::{header, Request, Version};
+use serde_json::Value;
+use std::{collections::VecDeque, fs};
+use url::form_urlencoded;
+
+/// 把构造好的请求渲染成等价的 curl 命令。digest 认证需要先拿到 challenge，
+/// 请求中还没有 Authorization 头，所以交给 curl 的 --digest 处理。
+/// multipart 和非 UTF-8 的内容没法在命令行中原样表示，直接报错而不是生成错误的命令
+pub fn to_curl(req: &Request, auth: Option<&Auth>, verify: &Verify) -> Result<String> {
+    let mut args = vec!["curl".to_string()];
+    if req.method() != "GET" {
+        args.extend(["-X".into(), req.method().to_string()]);
+    }
+    match verify {
+        Verify::Yes => {}
+        Verify::No => args.push("-k".into()),
+        Verify::Bundle(path) => {
+            args.extend(["--cacert".into(), path.to_string_lossy().to_string()])
+        }
+    }
+    match req.version() {
+        Version::HTTP_10 => args.push("--http1.0".into()),
+        Version::HTTP_2 => args.push("--http2-prior-knowledge".into()),
+        _ => {}
+    }
+    args.push(req.url().to_string());
+    for (name, value) in req.headers() {
+        let value = value.to_str().map_err(|_| {
+            anyhow!(
+                "Header {} is not valid UTF-8 and cannot be converted to curl",
+                name
+            )
+        })?;
+        args.extend(["-H".into(), format!("{}: {}", name, value)]);
+    }
+    if let Some(auth) = auth.filter(|a| a.auth_type == AuthType::Digest) {
+        args.extend([
+            "--digest".into(),
+            "-u".into(),
+            format!("{}:{}", auth.user, auth.password),
+        ]);
+    }
+    if let Some(body) = req.body().and_then(|b| b.as_bytes()) {
+        let multipart = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("multipart/"));
+        if multipart {
+            return Err(anyhow!(
+                "Multipart bodies cannot be converted to curl, use curl -F instead"
+            ));
+        }
+        let body = std::str::from_utf8(body).map_err(|_| {
+            anyhow!("Binary body cannot be converted to curl, save it to a file and use curl --data-binary @file")
+        })?;
+        // --data-raw 不会把 @ 开头的内容当作文件名
+        args.extend(["--data-raw".into(), body.to_string()]);
+    }
+    Ok(shell_words::join(args))
+}
+
+/// 解析 curl 命令时收集到的信息
+#[derive(Debug, Default)]
+struct Curl {
+    method: Option<String>,
+    url: Option<String>,
+    headers: Vec<(String, String)>,
+    data: Vec<String>,
+    json: bool,
+    form: Vec<String>,
+    get: bool,
+    head: bool,
+    /// 可以直接对应到 httpie 全局选项的参数
+    options: Vec<String>,
+}
+
+/// 把 curl 命令转换成等价的 httpie 参数：全局选项之后是 `request METHOD URL items...`。
+/// command 只有一个元素时按 shell 的规则拆分，方便直接粘贴整条命令
+pub fn from_curl(command: &[String]) -> Result<Vec<String>> {
+    let mut tokens: VecDeque<String> = match command {
+        [command] => shell_words::split(command)
+            .with_context(|| format!("Failed to parse curl command {}", command))?
+            .into(),
+        _ => command.iter().cloned().collect(),
+    };
+    if tokens.front().is_some_and(|t| t == "curl") {
+        tokens.pop_front();
+    }
+
+    let mut curl = Curl::default();
+    while let Some(token) = tokens.pop_front() {
+        let name = match token.strip_prefix("--") {
+            Some(name) => name.to_string(),
+            None if token.starts_with('-') && token.len() > 1 => {
+                // 短选项可以带上参数（-XPOST），也可以合并多个开关（-sSL）
+                let mut chars = token[1..].chars();
+                let c = chars.next().unwrap();
+                let rest = chars.as_str();
+                let name =
+                    short_option(c).ok_or_else(|| anyhow!("Unsupported curl option -{}", c))?;
+                if !rest.is_empty() {
+                    match takes_value(name) {
+                        true => tokens.push_front(rest.to_string()),
+                        false => tokens.push_front(format!("-{}", rest)),
+                    }
+                }
+                name.to_string()
+            }
+            None => {
+                curl.url = Some(token);
+                continue;
+            }
+        };
+        let value = match takes_value(&name) {
+            true => Some(
+                tokens
+                    .pop_front()
+                    .ok_or_else(|| anyhow!("Missing value for curl option --{}", name))?,
+            ),
+            false => None,
+        };
+        curl.apply(&name, value)?;
+    }
+    curl.into_args()
+}
+
+fn short_option(c: char) -> Option<&'static str> {
+    Some(match c {
+        'X' => "request",
+        'H' => "header",
+        'd' => "data",
+        'F' => "form",
+        'u' => "user",
+        'I' => "head",
+        'G' => "get",
+        'L' => "location",
+        'k' => "insecure",
+        'E' => "cert",
+        'x' => "proxy",
+        'm' => "max-time",
+        'A' => "user-agent",
+        'e' => "referer",
+        'b' => "cookie",
+        's' => "silent",
+        'S' => "show-error",
+        'i' => "include",
+        'v' => "verbose",
+        'f' => "fail",
+        _ => return None,
+    })
+}
+
+fn takes_value(name: &str) -> bool {
+    matches!(
+        name,
+        "request"
+            | "header"
+            | "data"
+            | "data-raw"
+            | "data-binary"
+            | "data-ascii"
+            | "data-urlencode"
+            | "json"
+            | "form"
+            | "user"
+            | "cert"
+            | "key"
+            | "cacert"
+            | "proxy"
+            | "max-time"
+            | "max-redirs"
+            | "retry"
+            | "user-agent"
+            | "referer"
+            | "cookie"
+            | "url"
+    )
+}
+
+impl Curl {
+    fn apply(&mut self, name: &str, value: Option<String>) -> Result<()> {
+        let value = value.unwrap_or_default();
+        match name {
+            "request" => self.method = Some(value.to_ascii_uppercase()),
+            "url" => self.url = Some(value),
+            "header" => {
+                // curl 中 `-H 'Name;'` 表示空值的请求头，`-H 'Name:'` 表示删除请求头
+                match (value.split_once(':'), value.strip_suffix(';')) {
+                    (Some((_, v)), _) if v.trim().is_empty() => {}
+                    (Some((k, v)), _) => self.headers.push((k.trim().into(), v.trim().into())),
+                    (None, Some(k)) => self.headers.push((k.trim().into(), String::new())),
+                    _ => return Err(anyhow!("Invalid curl header {}", value)),
+                }
+            }
+            "user-agent" => self.headers.push(("User-Agent".into(), value)),
+            "referer" => self.headers.push(("Referer".into(), value)),
+            "cookie" => self.headers.push(("Cookie".into(), value)),
+            "data" | "data-ascii" | "data-binary" => match value.strip_prefix('@') {
+                Some(path) => self.data.push(
+                    fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?,
+                ),
+                None => self.data.push(value),
+            },
+            "data-raw" => self.data.push(value),
+            "data-urlencode" => self.data.push(urlencode(&value)),
+            "json" => {
+                self.json = true;
+                self.data.push(value);
+            }
+            "form" => self.form.push(value),
+            "get" => self.get = true,
+            "head" => self.head = true,
+            "user" => self.options.push(format!("--auth={}", value)),
+            "digest" => self.options.push("--auth-type=digest".into()),
+            "location" => self.options.push("--follow".into()),
+            "max-redirs" => self.options.push(format!("--max-redirects={}", value)),
+            "insecure" => self.options.push("--verify=no".into()),
+            "cacert" => self.options.push(format!("--verify={}", value)),
+            "cert" => self.options.push(format!("--cert={}", value)),
+            "key" => self.options.push(format!("--cert-key={}", value)),
+            "proxy" => self.options.push(format!("--proxy=all:{}", value)),
+            "max-time" => self.options.push(format!("--timeout={}", value)),
+            "retry" => self.options.push(format!("--retries={}", value)),
+            "http1.0" => self.options.push("--http-version=1.0".into()),
+            "http1.1" => self.options.push("--http-version=1.1".into()),
+            "http2-prior-knowledge" => self.options.push("--http-version=2".into()),
+            // 只影响 curl 自己的输出，或者和默认行为一样（--http2 通过 ALPN 协商）
+            "basic" | "compressed" | "http2" | "silent" | "show-error" | "include" | "verbose"
+            | "fail" | "no-progress-meter" => {}
+            _ => return Err(anyhow!("Unsupported curl option --{}", name)),
+        }
+        Ok(())
+    }
+
+    fn into_args(mut self) -> Result<Vec<String>> {
+        let url = self
+            .url
+            .take()
+            .ok_or_else(|| anyhow!("Missing URL in curl command"))?;
+        let has_body = !self.data.is_empty() || !self.form.is_empty();
+        let method = match (self.method.take(), self.head, self.get || !has_body) {
+            (Some(method), _, _) => method,
+            (None, true, _) => "HEAD".into(),
+            (None, _, true) => "GET".into(),
+            _ => "POST".into(),
+        };
+
+        // Content-Type 由 body 决定，不作为请求头传过去，避免出现两个 Content-Type
+        let content_type = self
+            .headers
+            .iter()
+            .position(|(k, _)| k.eq_ignore_ascii_case(header::CONTENT_TYPE.as_str()))
+            .map(|i| self.headers.remove(i).1)
+            .unwrap_or_else(|| match self.json {
+                true => mime::APPLICATION_JSON.to_string(),
+                false => mime::APPLICATION_WWW_FORM_URLENCODED.to_string(),
+            });
+
+        let mut args = std::mem::take(&mut self.options);
+        let mut items = Vec::new();
+        for (k, v) in &self.headers {
+            if v.starts_with('=') {
+                return Err(anyhow!("Cannot convert header {}: {}", k, v));
+            }
+            items.push(format!("{}:{}", escape(k), v));
+        }
+
+        let data = self.data.join("&");
+        if self.get {
+            // -G 把 -d 的数据放到 query string 中
+            for (k, v) in form_urlencoded::parse(data.as_bytes()) {
+                items.push(format!("{}=={}", escape(&k), v));
+            }
+        } else if !self.form.is_empty() {
+            args.push("--multipart".into());
+            for field in &self.form {
+                let (k, v) = field
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Invalid curl form field {}", field))?;
+                items.push(match (v.strip_prefix('@'), v.strip_prefix('<')) {
+                    (Some(path), _) => format!("{}@{}", escape(k), path),
+                    (_, Some(path)) => format!("{}=@{}", escape(k), path),
+                    _ => format!("{}={}", escape(k), escape_value(v)?),
+                });
+            }
+        } else if !data.is_empty() {
+            match body_items(&data, &content_type) {
+                Some((form, body_items)) => {
+                    if form {
+                        args.push("--form".into());
+                    }
+                    items.extend(body_items);
+                }
+                // 无法用 request items 表达的 body 原样发送
+                None => args.extend([
+                    format!("--raw={}", data),
+                    format!("--content-type={}", content_type),
+                ]),
+            }
+        }
+
+        args.extend(["request".into(), method, url]);
+        args.extend(items);
+        Ok(args)
+    }
+}
+
+/// 尽量把 body 转换成 request items：JSON 对象转换成 `key:=value`，表单转换成 `key=value`。
+/// 返回的 bool 表示是否需要 --form
+fn body_items(data: &str, content_type: &str) -> Option<(bool, Vec<String>)> {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    if essence == mime::APPLICATION_JSON.essence_str() {
+        let Ok(Value::Object(map)) = serde_json::from_str(data) else {
+            return None;
+        };
+        let items = map
+            .iter()
+            .map(|(k, v)| format!("{}:={}", escape(k), v))
+            .collect();
+        return Some((false, items));
+    }
+    if essence == mime::APPLICATION_WWW_FORM_URLENCODED.essence_str() {
+        let items = form_urlencoded::parse(data.as_bytes())
+            .map(|(k, v)| Some(format!("{}={}", escape(&k), escape_value(&v).ok()?)))
+            .collect::<Option<Vec<_>>>()?;
+        return Some((true, items));
+    }
+    None
+}
+
+/// `-d name=content` 会对 content 做 URL 编码，没有 `=` 时对整个值编码
+fn urlencode(s: &str) -> String {
+    let encode = |v: &str| form_urlencoded::byte_serialize(v.as_bytes()).collect::<String>();
+    match s.split_once('=') {
+        Some((name, content)) => format!("{}={}", name, encode(content)),
+        None => encode(s),
+    }
+}
+
+/// key 中的分隔符需要用 `\` 转义
+fn escape(key: &str) -> String {
+    key.chars().fold(String::new(), |mut s, c| {
+        if matches!(c, '\\' | ':' | '=' | '@') {
+            s.push('\\');
+        }
+        s.push(c);
+        s
+    })
+}
+
+/// value 没有转义语法，`=` 或 `@` 开头的值会和分隔符连在一起被误解析
+fn escape_value(value: &str) -> Result<&str> {
+    match value.starts_with(['=', '@']) {
+        true => Err(anyhow!("Cannot convert form value {}", value)),
+        false => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Client;
+
+    fn args(command: &str) -> Vec<String> {
+        from_curl(&[command.to_string()]).unwrap()
+    }
+
+    #[test]
+    fn from_curl_should_convert_json_body() {
+        assert_eq!(
+            args(
+                "curl 'https://example.com/api' -H 'Content-Type: application/json' \
+                 -H 'X-Token: abc' --data-raw '{\"a\":1,\"b\":\"x\"}' --compressed -sSL"
+            ),
+            vec![
+                "--follow",
+                "request",
+                "POST",
+                "https://example.com/api",
+                "X-Token:abc",
+                "a:=1",
+                "b:=\"x\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn from_curl_should_convert_form_and_query() {
+        assert_eq!(
+            args("curl -XPUT -d 'a=1&b=x%20y' -u user:pass -k --http1.1 http://localhost/a"),
+            vec![
+                "--auth=user:pass",
+                "--verify=no",
+                "--http-version=1.1",
+                "--form",
+                "request",
+                "PUT",
+                "http://localhost/a",
+                "a=1",
+                "b=x y",
+            ]
+        );
+        assert_eq!(
+            args("curl -G -d q=rust --data-urlencode 'r=a b' http://localhost/s"),
+            vec!["request", "GET", "http://localhost/s", "q==rust", "r==a b"]
+        );
+        assert_eq!(
+            args("curl -F name=x -F 'doc=@/tmp/a.txt' http://localhost/up"),
+            vec![
+                "--multipart",
+                "request",
+                "POST",
+                "http://localhost/up",
+                "name=x",
+                "doc@/tmp/a.txt",
+            ]
+        );
+    }
+
+    #[test]
+    fn from_curl_should_fall_back_to_raw_body() {
+        assert_eq!(
+            args("curl -H 'content-type: text/plain' -d 'hello' http://localhost/"),
+            vec![
+                "--raw=hello",
+                "--content-type=text/plain",
+                "request",
+                "POST",
+                "http://localhost/",
+            ]
+        );
+        assert!(from_curl(&["curl --unknown http://localhost/".into()]).is_err());
+        assert!(from_curl(&["curl -H 'a: b'".into()]).is_err());
+    }
+
+    #[test]
+    fn to_curl_should_render_request() {
+        let req = Client::new()
+            .post("http://localhost/a?b=1")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body("{\"it's\":1}")
+            .version(Version::HTTP_2)
+            .build()
+            .unwrap();
+        let curl = to_curl(&req, None, &Verify::Yes).unwrap();
+        assert_eq!(
+            curl,
+            "curl -X POST --http2-prior-knowledge 'http://localhost/a?b=1' -H 'content-type: application/json' \
+             --data-raw '{\"it'\\''s\":1}'"
+        );
+        // 转换回来应该得到同样的请求
+        assert_eq!(
+            args(&curl),
+            vec![
+                "--http-version=2",
+                "request",
+                "POST",
+                "http://localhost/a?b=1",
+                "it's:=1"
+            ]
+        );
+
+        let req = Client::new()
+            .get("https://localhost/")
+            .header(header::COOKIE, "sid=abc")
+            .build()
+            .unwrap();
+        let curl = to_curl(&req, None, &Verify::No).unwrap();
+        assert_eq!(curl, "curl -k https://localhost/ -H 'cookie: sid=abc'");
+        assert_eq!(
+            args(&curl),
+            vec![
+                "--verify=no",
+                "request",
+                "GET",
+                "https://localhost/",
+                "cookie:sid=abc"
+            ]
+        );
+
+        let binary = Client::new()
+            .post("http://localhost/")
+            .body(vec![0xff, 0xfe])
+            .build()
+            .unwrap();
+        assert!(to_curl(&binary, None, &Verify::Yes).is_err());
+        let multipart = Client::new()
+            .post("http://localhost/")
+            .header(header::CONTENT_TYPE, "multipart/form-data; boundary=x")
+            .body("--x--\r\n")
+            .build()
+            .unwrap();
+        assert!(to_curl(&multipart, None, &Verify::Yes).is_err());
+        let header = Client::new()
+            .get("http://localhost/")
+            .header("x-a", header::HeaderValue::from_bytes(b"\xff").unwrap())
+            .build()
+            .unwrap();
+        assert!(to_curl(&header, None, &Verify::Yes).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use anyhow::{anyhow, Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use percent_encoding::percent_decode_str;
+use reqwest::{header, Response, StatusCode};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// --download 模式的配置
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub output: Option<PathBuf>,
+    pub resume: bool,
+}
+
+impl Download {
+    pub fn new(output: Option<PathBuf>, resume: bool) -> Result<Self> {
+        // 和 httpie 一样，续传时必须明确指定输出文件，避免续传到同名的其他文件上
+        if resume && output.is_none() {
+            return Err(anyhow!("--continue requires --output"));
+        }
+        Ok(Self { output, resume })
+    }
+
+    /// 续传时需要带上的 Range 头，文件不存在或为空时从头下载
+    pub fn range(&self) -> Option<String> {
+        if !self.resume {
+            return None;
+        }
+        let len = fs::metadata(self.output.as_ref()?).ok()?.len();
+        (len > 0).then(|| format!("bytes={}-", len))
+    }
+
+    /// 把响应 body 流式写入文件，并在 stderr 上显示进度、速率和剩余时间
+    pub async fn save(&self, mut resp: Response) -> Result<()> {
+        let path = match &self.output {
+            Some(path) => path.clone(),
+            None => unique_path(&filename(&resp)),
+        };
+        let existing = match self.resume {
+            true => fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+            false => 0,
+        };
+
+        if existing > 0 && resp.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            eprintln!("{} is already fully downloaded", path.display());
+            return Ok(());
+        }
+        if !resp.status().is_success() {
+            return Err(anyhow!("Download failed with {}", resp.status()));
+        }
+
+        let offset = match (existing, resp.status()) {
+            (0, _) => 0,
+            (_, StatusCode::PARTIAL_CONTENT) => {
+                let range = resp
+                    .headers()
+                    .get(header::CONTENT_RANGE)
+                    .and_then(|v| v.to_str().ok())
+                    .ok_or_else(|| anyhow!("Missing Content-Range in 206 response"))?;
+                let (start, _) = parse_content_range(range)?;
+                if start != existing {
+                    return Err(anyhow!(
+                        "Invalid Content-Range {}, expect it to start at byte {}",
+                        range,
+                        existing
+                    ));
+                }
+                start
+            }
+            // 服务器不支持 Range 时会返回完整内容，只能从头开始下载
+            _ => {
+                eprintln!("Server does not support resuming, downloading from scratch");
+                0
+            }
+        };
+
+        let total = resp.content_length().map(|len| len + offset);
+        let pb = progress_bar(total);
+        pb.set_position(offset);
+        pb.set_message(path.display().to_string());
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(offset > 0)
+            .truncate(offset == 0)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        while let Some(chunk) = resp.chunk().await? {
+            file.write_all(&chunk)?;
+            pb.inc(chunk.len() as u64);
+        }
+        pb.finish();
+
+        eprintln!("Downloaded to {}", path.display());
+        Ok(())
+    }
+}
+
+fn progress_bar(total: Option<u64>) -> ProgressBar {
+    match total {
+        Some(total) => ProgressBar::new(total).with_style(
+            ProgressStyle::with_template(
+                "{msg} [{bar:40}] {bytes}/{total_bytes} ({bytes_per_sec}, ETA {eta})",
+            )
+            .unwrap()
+            .progress_chars("=> "),
+        ),
+        None => ProgressBar::new_spinner().with_style(
+            ProgressStyle::with_template("{spinner} {msg} {bytes} ({bytes_per_sec})").unwrap(),
+        ),
+    }
+}
+
+/// 优先使用 Content-Disposition 中的文件名，否则使用 URL 的最后一段
+fn filename(resp: &Response) -> String {
+    let name = resp
+        .headers()
+        .get(header::CONTENT_DISPOSITION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_content_disposition)
+        .or_else(|| {
+            resp.url()
+                .path_segments()
+                .and_then(|mut s| s.next_back())
+                .map(|s| percent_decode_str(s).decode_utf8_lossy().to_string())
+        });
+
+    // 只取文件名部分，防止服务器返回 ../../etc/passwd 这样的路径
+    name.as_deref()
+        .map(Path::new)
+        .and_then(Path::file_name)
+        .map(|s| s.to_string_lossy().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "index".into())
+}
+
+/// 解析 `attachment; filename="a.txt"` 或 `attachment; filename*=UTF-8''a%20b.txt`
+fn parse_content_disposition(s: &str) -> Option<String> {
+    let mut filename = None;
+    for param in s.split(';').map(str::trim) {
+        let Some((k, v)) = param.split_once('=') else {
+            continue;
+        };
+        match k.trim().to_ascii_lowercase().as_str() {
+            // filename* 支持非 ASCII 字符，优先级更高
+            "filename*" => {
+                let v = v.trim().trim_matches('"');
+                let encoded = v.splitn(3, '\'').nth(2).unwrap_or(v);
+                return Some(percent_decode_str(encoded).decode_utf8_lossy().to_string());
+            }
+            "filename" => filename = Some(v.trim().trim_matches('"').to_string()),
+            _ => {}
+        }
+    }
+    filename
+}
+
+/// 解析 `bytes 100-199/200`，返回起始位置和总长度（未知时为 None）
+fn parse_content_range(s: &str) -> Result<(u64, Option<u64>)> {
+    let err = || anyhow!("Invalid Content-Range {}", s);
+    let range = s.trim().strip_prefix("bytes ").ok_or_else(err)?;
+    let (range, total) = range.split_once('/').ok_or_else(err)?;
+    let (start, _) = range.split_once('-').ok_or_else(err)?;
+    let total = match total {
+        "*" => None,
+        total => Some(total.parse().map_err(|_| err())?),
+    };
+    Ok((start.parse().map_err(|_| err())?, total))
+}
+
+/// 文件已存在时在文件名后加上 -1、-2 ……，不覆盖已有文件
+fn unique_path(name: &str) -> PathBuf {
+    let path = PathBuf::from(name);
+    (1..)
+        .map(|i| match i {
+            1 => path.clone(),
+            i => PathBuf::from(format!("{}-{}", name, i - 1)),
+        })
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_content_disposition_should_work() {
+        assert_eq!(
+            parse_content_disposition("attachment; filename=\"report.pdf\"").as_deref(),
+            Some("report.pdf")
+        );
+        assert_eq!(
+            parse_content_disposition(
+                "attachment; filename=\"a.txt\"; filename*=UTF-8''%E4%BD%A0%E5%A5%BD.txt"
+            )
+            .as_deref(),
+            Some("你好.txt")
+        );
+        assert_eq!(parse_content_disposition("inline"), None);
+    }
+
+    #[test]
+    fn parse_content_range_should_work() {
+        assert_eq!(
+            parse_content_range("bytes 100-199/200").unwrap(),
+            (100, Some(200))
+        );
+        assert_eq!(parse_content_range("bytes 0-9/*").unwrap(), (0, None));
+        assert!(parse_content_range("items 0-9/10").is_err());
+    }
+
+    #[test]
+    fn continue_should_require_output() {
+        assert!(Download::new(None, true).is_err());
+        assert!(Download::new(None, false).is_ok());
+    }
+
+    #[test]
+    fn range_should_start_from_existing_file() {
+        let path = std::env::temp_dir().join("httpie-download-range-test.bin");
+        fs::write(&path, b"12345").unwrap();
+        let download = Download::new(Some(path.clone()), true).unwrap();
+        assert_eq!(download.range().as_deref(), Some("bytes=5-"));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(download.range(), None);
+    }
+}
@@ -0,0 +1,80 @@
+use reqwest::StatusCode;
+use std::process::ExitCode;
+
+/// 进程退出码，和 httpie 保持一致，方便在脚本中区分失败的原因（httpie 中 6 表示重定向次数过多，
+/// 这里重定向错误和其他错误一样返回 1）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    Success = 0,
+    Error = 1,
+    Timeout = 2,
+    Error3xx = 3,
+    Error4xx = 4,
+    Error5xx = 5,
+    ConnectionError = 7,
+}
+
+impl ExitStatus {
+    /// --check-status 时根据响应状态码得到退出码，--follow 时跟随后的 3xx 不算错误
+    pub fn from_status(status: StatusCode, follow: bool) -> Self {
+        match status.as_u16() {
+            300..=399 if !follow => ExitStatus::Error3xx,
+            400..=499 => ExitStatus::Error4xx,
+            500..=599 => ExitStatus::Error5xx,
+            _ => ExitStatus::Success,
+        }
+    }
+
+    /// 超时和连接失败有单独的退出码，其他错误统一为 1
+    pub fn from_error(err: &anyhow::Error) -> Self {
+        let reqwest_err = err.chain().find_map(|e| e.downcast_ref::<reqwest::Error>());
+        match reqwest_err {
+            Some(e) if e.is_timeout() => ExitStatus::Timeout,
+            Some(e) if e.is_connect() => ExitStatus::ConnectionError,
+            _ => ExitStatus::Error,
+        }
+    }
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        ExitCode::from(status as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_status_should_map_status_classes() {
+        let status = |code| ExitStatus::from_status(StatusCode::from_u16(code).unwrap(), false);
+        assert_eq!(status(200), ExitStatus::Success);
+        assert_eq!(status(302), ExitStatus::Error3xx);
+        assert_eq!(status(404), ExitStatus::Error4xx);
+        assert_eq!(status(503), ExitStatus::Error5xx);
+        assert_eq!(
+            ExitStatus::from_status(StatusCode::NOT_MODIFIED, true),
+            ExitStatus::Success
+        );
+    }
+
+    #[tokio::test]
+    async fn from_error_should_detect_connection_errors() {
+        // 先占用一个端口再释放，保证没有服务在监听
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let err = reqwest::get(format!("http://127.0.0.1:{}/", port))
+            .await
+            .unwrap_err();
+        let err = anyhow::Error::from(err).context("request failed");
+        assert_eq!(ExitStatus::from_error(&err), ExitStatus::ConnectionError);
+        assert_eq!(
+            ExitStatus::from_error(&anyhow::anyhow!("boom")),
+            ExitStatus::Error
+        );
+    }
+}
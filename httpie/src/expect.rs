@@ -0,0 +1,321 @@
+use crate::filter::Filter;
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use regex::Regex;
+use reqwest::{header::HeaderMap, StatusCode};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// --expect-status 的取值：具体的状态码，或者 `2xx` 这样的一类状态码
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatusPattern {
+    Exact(u16),
+    Class(u16),
+}
+
+impl FromStr for StatusPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let err = || {
+            anyhow!(
+                "Invalid status {}, expect a code like 200 or a class like 2xx",
+                s
+            )
+        };
+        if let Some(class) = s.strip_suffix("xx").or_else(|| s.strip_suffix("XX")) {
+            return match class.parse() {
+                Ok(class @ 1..=5) => Ok(StatusPattern::Class(class)),
+                _ => Err(err()),
+            };
+        }
+        match s.parse() {
+            Ok(code @ 100..=599) => Ok(StatusPattern::Exact(code)),
+            _ => Err(err()),
+        }
+    }
+}
+
+impl StatusPattern {
+    fn matches(&self, status: StatusCode) -> bool {
+        match self {
+            StatusPattern::Exact(code) => status.as_u16() == *code,
+            StatusPattern::Class(class) => status.as_u16() / 100 == *class,
+        }
+    }
+}
+
+impl std::fmt::Display for StatusPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatusPattern::Exact(code) => write!(f, "{}", code),
+            StatusPattern::Class(class) => write!(f, "{}xx", class),
+        }
+    }
+}
+
+/// --expect-header Name:regex
+#[derive(Debug, Clone)]
+pub struct HeaderExpect {
+    pub name: String,
+    pub pattern: Regex,
+}
+
+impl FromStr for HeaderExpect {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, pattern) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid header expectation {}, expect Name:regex", s))?;
+        Ok(Self {
+            name: name.trim().to_string(),
+            pattern: Regex::new(pattern.trim())?,
+        })
+    }
+}
+
+/// --expect-json path=value，path 使用 --filter 的语法，value 不是合法 JSON 时当作字符串
+#[derive(Debug, Clone)]
+pub struct JsonExpect {
+    pub path: String,
+    pub filter: Filter,
+    pub value: Value,
+}
+
+impl FromStr for JsonExpect {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // path 中的 `["a=b"]` 里也可能有 `=`，所以跳过方括号中的内容
+        let mut depth = 0;
+        let pos = s
+            .char_indices()
+            .find(|&(_, c)| {
+                match c {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    _ => {}
+                }
+                c == '=' && depth == 0
+            })
+            .map(|(i, _)| i)
+            .ok_or_else(|| anyhow!("Invalid JSON expectation {}, expect path=value", s))?;
+        let (path, value) = (s[..pos].trim(), &s[pos + 1..]);
+        Ok(Self {
+            path: path.to_string(),
+            filter: path.parse()?,
+            value: serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.into())),
+        })
+    }
+}
+
+pub fn parse_status_pattern(s: &str) -> Result<StatusPattern> {
+    s.parse()
+}
+
+pub fn parse_header_expect(s: &str) -> Result<HeaderExpect> {
+    s.parse()
+}
+
+pub fn parse_json_expect(s: &str) -> Result<JsonExpect> {
+    s.parse()
+}
+
+/// 对响应的断言，全部检查完之后一起报告失败的项
+#[derive(Debug, Clone, Default)]
+pub struct Expect {
+    pub status: Vec<StatusPattern>,
+    pub headers: Vec<HeaderExpect>,
+    pub json: Vec<JsonExpect>,
+}
+
+impl Expect {
+    /// 检查响应，不满足时返回的错误中包含每一项的期望值和实际值。body 为 None 表示二进制内容
+    pub fn check(&self, status: StatusCode, headers: &HeaderMap, body: Option<&str>) -> Result<()> {
+        let mut failures = Vec::new();
+
+        if !self.status.is_empty() && !self.status.iter().any(|p| p.matches(status)) {
+            let expected: Vec<_> = self.status.iter().map(|p| p.to_string()).collect();
+            failures.push(format!(
+                "status\n{}",
+                diff(&expected.join(" or "), &status.to_string())
+            ));
+        }
+
+        for expect in &self.headers {
+            let values: Vec<_> = headers
+                .get_all(&expect.name)
+                .iter()
+                .map(|v| String::from_utf8_lossy(v.as_bytes()).to_string())
+                .collect();
+            if !values.iter().any(|v| expect.pattern.is_match(v)) {
+                let actual = match values.is_empty() {
+                    true => "<missing>".to_string(),
+                    false => values.join("\n"),
+                };
+                failures.push(format!(
+                    "header {}\n{}",
+                    expect.name,
+                    diff(&format!("/{}/", expect.pattern), &actual)
+                ));
+            }
+        }
+
+        if !self.json.is_empty() {
+            let Some(body) = body else {
+                return Err(anyhow!("Response body is binary, not JSON"));
+            };
+            let json: Value = match serde_json::from_str(body) {
+                Ok(json) => json,
+                Err(e) => return Err(anyhow!("Response body is not JSON: {}", e)),
+            };
+            for expect in &self.json {
+                let actual = expect.filter.apply(&json);
+                if actual != expect.value {
+                    failures.push(format!(
+                        "json {}\n{}",
+                        expect.path,
+                        diff(&pretty(&expect.value), &pretty(&actual))
+                    ));
+                }
+            }
+        }
+
+        match failures.len() {
+            0 => Ok(()),
+            n => Err(anyhow!(
+                "{} expectation{} failed:\n\n{}",
+                n,
+                if n == 1 { "" } else { "s" },
+                failures.join("\n\n")
+            )),
+        }
+    }
+}
+
+/// 格式化成多行 JSON，对象的键按字母排序，这样键的顺序不同不会出现在 diff 中
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(&sorted(value)).unwrap_or_else(|_| value.to_string())
+}
+
+fn sorted(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k.clone(), sorted(v)))
+                    .collect(),
+            )
+        }
+        Value::Array(arr) => Value::Array(arr.iter().map(sorted).collect()),
+        v => v.clone(),
+    }
+}
+
+/// 按行比较期望值和实际值：`-` 开头的是期望值中有而实际值中没有的行，`+` 开头的则相反
+fn diff(expected: &str, actual: &str) -> String {
+    let a: Vec<&str> = expected.lines().collect();
+    let b: Vec<&str> = actual.lines().collect();
+
+    // 最长公共子序列，lcs[i][j] 是 a[i..] 和 b[j..] 的 LCS 长度
+    let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = match a[i] == b[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            lines.push(format!("  {}", a[i]));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(format!("- {}", a[i]).red().to_string());
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", b[j]).green().to_string());
+            j += 1;
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header;
+
+    fn expect(status: &[&str], headers: &[&str], json: &[&str]) -> Expect {
+        Expect {
+            status: status.iter().map(|s| s.parse().unwrap()).collect(),
+            headers: headers.iter().map(|s| s.parse().unwrap()).collect(),
+            json: json.iter().map(|s| s.parse().unwrap()).collect(),
+        }
+    }
+
+    #[test]
+    fn check_should_pass_matching_response() {
+        colored::control::set_override(false);
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+        let body = r#"{"data": {"items": [{"id": 1}], "name": "a=b"}}"#;
+        let expect = expect(
+            &["2xx"],
+            &["Content-Type:^application/json"],
+            &[
+                "data.items[0].id=1",
+                "data.name=a=b",
+                "data.items[*].id=[1]",
+            ],
+        );
+        expect.check(StatusCode::OK, &headers, Some(body)).unwrap();
+        assert!(expect.check(StatusCode::OK, &headers, None).is_err());
+    }
+
+    #[test]
+    fn check_should_report_all_failures() {
+        colored::control::set_override(false);
+        let body = r#"{"user": {"id": 1, "roles": ["admin", "dev"]}}"#;
+        let expect = expect(
+            &["200", "201"],
+            &["X-Token:.+"],
+            &[r#"user={"id": 1, "roles": ["admin", "ops"]}"#],
+        );
+        let err = expect
+            .check(StatusCode::NOT_FOUND, &HeaderMap::new(), Some(body))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "3 expectations failed:\n\n\
+             status\n- 200 or 201\n+ 404 Not Found\n\n\
+             header X-Token\n- /.+/\n+ <missing>\n\n\
+             json user\n  {\n    \"id\": 1,\n    \"roles\": [\n      \"admin\",\n\
+             -     \"ops\"\n+     \"dev\"\n    ]\n  }"
+        );
+    }
+
+    #[test]
+    fn parse_should_reject_invalid_expectations() {
+        assert!(parse_status_pattern("6xx").is_err());
+        assert!(parse_status_pattern("abc").is_err());
+        assert!(parse_header_expect("X-Token").is_err());
+        assert!(parse_header_expect("X-Token:(").is_err());
+        assert!(parse_json_expect("data.id").is_err());
+        assert_eq!(
+            parse_json_expect("data[\"a=b\"]=x").unwrap().value,
+            Value::String("x".into())
+        );
+    }
+}
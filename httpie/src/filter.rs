@@ -0,0 +1,225 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::{
+    iter::Peekable,
+    str::{Chars, FromStr},
+};
+
+/// 路径中的一段
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// `.name` 或 `["name"]`
+    Field(String),
+    /// `[0]`，负数表示从末尾开始
+    Index(i64),
+    /// `.*` 或 `[*]`，对象的所有值或数组的所有元素
+    Wildcard,
+    /// `[start:end]`，和 Python 一样左闭右开，支持负数
+    Slice(Option<i64>, Option<i64>),
+}
+
+/// --filter 使用的 JSONPath 子集，例如 `$.data.items[*].name`、`items[-1]`、`items[1:3]`，
+/// 开头的 `$` 和 `.` 可以省略
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    segments: Vec<Segment>,
+}
+
+impl Filter {
+    /// 应用到 JSON 上。路径中包含通配符或切片时结果可能有多个，总是返回数组；
+    /// 否则返回匹配到的值，没有匹配时返回 null
+    pub fn apply(&self, value: &Value) -> Value {
+        let mut values = vec![value];
+        for segment in &self.segments {
+            values = values
+                .into_iter()
+                .flat_map(|v| select(v, segment))
+                .collect();
+        }
+        let multiple = self
+            .segments
+            .iter()
+            .any(|s| matches!(s, Segment::Wildcard | Segment::Slice(..)));
+        match multiple {
+            true => Value::Array(values.into_iter().cloned().collect()),
+            false => values.first().map_or(Value::Null, |v| (*v).clone()),
+        }
+    }
+}
+
+fn select<'a>(value: &'a Value, segment: &Segment) -> Vec<&'a Value> {
+    match (segment, value) {
+        (Segment::Field(name), Value::Object(map)) => map.get(name).into_iter().collect(),
+        (Segment::Index(i), Value::Array(arr)) => index(*i, arr.len())
+            .and_then(|i| arr.get(i))
+            .into_iter()
+            .collect(),
+        (Segment::Wildcard, Value::Object(map)) => map.values().collect(),
+        (Segment::Wildcard, Value::Array(arr)) => arr.iter().collect(),
+        (Segment::Slice(start, end), Value::Array(arr)) => {
+            let len = arr.len() as i64;
+            let bound = |i: i64| match i < 0 {
+                true => (len + i).max(0),
+                false => i.min(len),
+            };
+            let start = start.map_or(0, bound);
+            let end = end.map_or(len, bound);
+            match start < end {
+                true => arr[start as usize..end as usize].iter().collect(),
+                false => vec![],
+            }
+        }
+        _ => vec![],
+    }
+}
+
+/// 负数下标从末尾开始计算，越界时返回 None
+fn index(i: i64, len: usize) -> Option<usize> {
+    match i < 0 {
+        true => len.checked_sub(i.unsigned_abs() as usize),
+        false => Some(i as usize),
+    }
+}
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.trim().chars().peekable();
+        if chars.peek() == Some(&'$') {
+            chars.next();
+        }
+        let mut segments = Vec::new();
+        // 第一个字段前面可以不写 `.`
+        if chars.peek().is_some_and(|&c| c != '.' && c != '[') {
+            segments.push(parse_name(&mut chars, s)?);
+        }
+        while let Some(c) = chars.next() {
+            let segment = match c {
+                '.' => parse_name(&mut chars, s)?,
+                '[' => parse_bracket(&mut chars, s)?,
+                c => return Err(anyhow!("Invalid filter {}: unexpected '{}'", s, c)),
+            };
+            segments.push(segment);
+        }
+        Ok(Self { segments })
+    }
+}
+
+pub fn parse_filter(s: &str) -> Result<Filter> {
+    s.parse()
+}
+
+/// 解析 `.` 之后的字段名或 `*`
+fn parse_name(chars: &mut Peekable<Chars>, s: &str) -> Result<Segment> {
+    let mut name = String::new();
+    while let Some(&c) = chars.peek() {
+        if c == '.' || c == '[' {
+            break;
+        }
+        name.push(c);
+        chars.next();
+    }
+    match name.as_str() {
+        "" => Err(anyhow!("Invalid filter {}: missing field name", s)),
+        "*" => Ok(Segment::Wildcard),
+        _ => Ok(Segment::Field(name)),
+    }
+}
+
+/// 解析 `[` 和 `]` 之间的内容：`*`、下标、切片或者带引号的字段名
+fn parse_bracket(chars: &mut Peekable<Chars>, s: &str) -> Result<Segment> {
+    let err = |msg: &str| anyhow!("Invalid filter {}: {}", s, msg);
+    if let Some(quote) = chars.next_if(|&c| c == '"' || c == '\'') {
+        let mut name = String::new();
+        loop {
+            match chars.next() {
+                Some('\\') => name.extend(chars.next()),
+                Some(c) if c == quote => break,
+                Some(c) => name.push(c),
+                None => return Err(err("unterminated string")),
+            }
+        }
+        return match chars.next() {
+            Some(']') => Ok(Segment::Field(name)),
+            _ => Err(err("missing ']'")),
+        };
+    }
+
+    let mut inner = String::new();
+    loop {
+        match chars.next() {
+            Some(']') => break,
+            Some(c) => inner.push(c),
+            None => return Err(err("missing ']'")),
+        }
+    }
+    let inner = inner.trim();
+    let int = |v: &str| {
+        v.trim()
+            .parse::<i64>()
+            .map_err(|_| err(&format!("invalid index {}", v)))
+    };
+    let opt_int = |v: &str| match v.trim() {
+        "" => Ok(None),
+        v => int(v).map(Some),
+    };
+    match inner.split_once(':') {
+        _ if inner == "*" => Ok(Segment::Wildcard),
+        Some((start, end)) => Ok(Segment::Slice(opt_int(start)?, opt_int(end)?)),
+        None => Ok(Segment::Index(int(inner)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn data() -> Value {
+        json!({
+            "data": {
+                "items": [
+                    {"id": 1, "name": "a"},
+                    {"id": 2, "name": "b"},
+                    {"id": 3, "name": "c"}
+                ],
+                "total": 3,
+                "odd key": true
+            }
+        })
+    }
+
+    fn apply(filter: &str) -> Value {
+        filter.parse::<Filter>().unwrap().apply(&data())
+    }
+
+    #[test]
+    fn filter_should_access_fields_and_indexes() {
+        assert_eq!(apply("$.data.total"), json!(3));
+        assert_eq!(apply("data.items[0].name"), json!("a"));
+        assert_eq!(apply(".data.items[-1].id"), json!(3));
+        assert_eq!(apply("data[\"odd key\"]"), json!(true));
+        assert_eq!(apply("data.items[5]"), Value::Null);
+        assert_eq!(apply("data.missing"), Value::Null);
+        assert_eq!(apply("$"), data());
+    }
+
+    #[test]
+    fn filter_should_support_wildcards_and_slices() {
+        assert_eq!(apply("data.items[*].id"), json!([1, 2, 3]));
+        assert_eq!(apply("data.items.*.name"), json!(["a", "b", "c"]));
+        assert_eq!(apply("data.items[1:].id"), json!([2, 3]));
+        assert_eq!(apply("data.items[:-1].name"), json!(["a", "b"]));
+        assert_eq!(apply("data.items[2:1]"), json!([]));
+        assert_eq!(apply("data.*"), json!([data()["data"]["items"], 3, true]));
+    }
+
+    #[test]
+    fn parse_filter_should_reject_invalid_expressions() {
+        assert!(parse_filter("data.").is_err());
+        assert!(parse_filter("data[0").is_err());
+        assert!(parse_filter("data[x]").is_err());
+        assert!(parse_filter("data['a]").is_err());
+    }
+}
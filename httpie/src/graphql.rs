@@ -0,0 +1,144 @@
+use crate::items::{self, RequestItem};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+
+/// 把 query 和 request items 组装成标准的 GraphQL 请求体 `{query, variables, operationName}`：
+/// 请求头和 query 参数原样保留，body 字段（`var=value`、`var:=json`）都作为 variables。
+/// query 以 `@` 开头时从文件中读取
+pub fn envelope(
+    query: &str,
+    operation: Option<&str>,
+    items: &[RequestItem],
+) -> Result<Vec<RequestItem>> {
+    let query = match query.strip_prefix('@') {
+        Some(path) => {
+            fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?
+        }
+        None => query.to_string(),
+    };
+    let mut envelope: Vec<_> = items
+        .iter()
+        .filter(|item| matches!(item, RequestItem::Header(..) | RequestItem::Query(..)))
+        .cloned()
+        .collect();
+    envelope.push(RequestItem::Json("query".into(), Value::String(query)));
+    if let Some(variables) = items::json_body(items)? {
+        envelope.push(RequestItem::Json("variables".into(), variables));
+    }
+    if let Some(operation) = operation {
+        envelope.push(RequestItem::Json(
+            "operationName".into(),
+            Value::String(operation.into()),
+        ));
+    }
+    Ok(envelope)
+}
+
+/// GraphQL 响应中的 data 以及整理成一行一条的错误信息
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub data: Option<Value>,
+    pub errors: Vec<String>,
+}
+
+/// 拆分 GraphQL 响应，既没有 data 也没有 errors 时不是 GraphQL 响应，返回 None
+pub fn parse_response(body: &str) -> Option<Response> {
+    let Value::Object(mut value) = serde_json::from_str(body).ok()? else {
+        return None;
+    };
+    let data = value.remove("data");
+    let errors = value.remove("errors");
+    if data.is_none() && errors.is_none() {
+        return None;
+    }
+    let errors = match errors {
+        Some(Value::Array(errors)) => errors.iter().map(format_error).collect(),
+        Some(error) => vec![format_error(&error)],
+        None => vec![],
+    };
+    Some(Response {
+        data: data.filter(|d| !d.is_null()),
+        errors,
+    })
+}
+
+/// 例如 `Cannot query field "x" on type "User" (line 2, column 3) at user.x`
+fn format_error(error: &Value) -> String {
+    let mut s = match &error["message"] {
+        Value::String(message) => message.clone(),
+        _ => error.to_string(),
+    };
+    if let Some(location) = error["locations"].get(0) {
+        s.push_str(&format!(
+            " (line {}, column {})",
+            location["line"], location["column"]
+        ));
+    }
+    if let Some(path) = error["path"].as_array() {
+        let path: Vec<_> = path
+            .iter()
+            .map(|p| match p {
+                Value::String(s) => s.clone(),
+                p => p.to_string(),
+            })
+            .collect();
+        s.push_str(&format!(" at {}", path.join(".")));
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn envelope_should_wrap_query_and_variables() {
+        let items: Vec<_> = ["Authorization:Bearer x", "id:=1", "name=a", "v==2"]
+            .iter()
+            .map(|s| items::parse_request_item(s).unwrap())
+            .collect();
+        let envelope = envelope(
+            "query($id: ID!) { user(id: $id) { name } }",
+            Some("q"),
+            &items,
+        )
+        .unwrap();
+        assert_eq!(
+            items::json_body(&envelope).unwrap().unwrap(),
+            json!({
+                "query": "query($id: ID!) { user(id: $id) { name } }",
+                "variables": {"id": 1, "name": "a"},
+                "operationName": "q"
+            })
+        );
+        assert_eq!(items::query(&envelope), vec![("v", "2")]);
+        assert!(items::headers(&envelope)
+            .unwrap()
+            .contains_key("authorization"));
+        assert!(super::envelope("@/nonexistent.graphql", None, &[]).is_err());
+    }
+
+    #[test]
+    fn parse_response_should_split_data_and_errors() {
+        let body = r#"{"data": {"user": null}, "errors": [{"message": "Not found",
+            "locations": [{"line": 2, "column": 3}], "path": ["user", 0, "name"]}]}"#;
+        assert_eq!(
+            parse_response(body),
+            Some(Response {
+                data: Some(json!({"user": null})),
+                errors: vec!["Not found (line 2, column 3) at user.0.name".into()],
+            })
+        );
+        assert_eq!(
+            parse_response(r#"{"data": null, "errors": [{"message": "x"}]}"#),
+            Some(Response {
+                data: None,
+                errors: vec!["x".into()],
+            })
+        );
+        assert_eq!(parse_response(r#"{"id": 1}"#), None);
+        assert_eq!(parse_response("[1]"), None);
+    }
+}
@@ -0,0 +1,466 @@
+use colored::Colorize;
+
+/// HTML 中没有结束标签的元素
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// HTML 中内容不按标签解析的元素
+const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+
+/// XML/HTML 文本切分后的片段，所有片段按顺序拼起来就是原文
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    /// `<name ...>`，自闭合的 `<name .../>` 以及 HTML 的 void 元素 closed 为 true
+    Open {
+        name: &'a str,
+        raw: &'a str,
+        closed: bool,
+    },
+    /// `</name>`
+    Close {
+        name: &'a str,
+        raw: &'a str,
+    },
+    /// `<!-- -->`、`<!DOCTYPE>`、`<![CDATA[ ]]>` 以及 `<?xml ?>`
+    Special(&'a str),
+    Text(&'a str),
+}
+
+fn tokenize(s: &str, html: bool) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let (mut pos, mut text_start) = (0, 0);
+    while let Some(offset) = s[pos..].find('<') {
+        let at = pos + offset;
+        // 不是标签开头的 `<`（比如 `a < b`）以及没有结束的标签都当作普通文本
+        let Some(len) = tag_len(&s[at..]) else {
+            pos = at + 1;
+            continue;
+        };
+        if text_start < at {
+            tokens.push(Token::Text(&s[text_start..at]));
+        }
+        let token = classify(&s[at..at + len], html);
+        pos = at + len;
+        text_start = pos;
+
+        // <script> 和 <style> 的内容原样保留，直到对应的结束标签
+        if let Token::Open {
+            name,
+            closed: false,
+            ..
+        } = token
+        {
+            if html
+                && RAW_TEXT_ELEMENTS
+                    .iter()
+                    .any(|e| e.eq_ignore_ascii_case(name))
+            {
+                let end = format!("</{}", name.to_ascii_lowercase());
+                let len = s[pos..]
+                    .to_ascii_lowercase()
+                    .find(&end)
+                    .unwrap_or(s.len() - pos);
+                tokens.push(token);
+                if len > 0 {
+                    tokens.push(Token::Text(&s[pos..pos + len]));
+                }
+                pos += len;
+                text_start = pos;
+                continue;
+            }
+        }
+        tokens.push(token);
+    }
+    if text_start < s.len() {
+        tokens.push(Token::Text(&s[text_start..]));
+    }
+    tokens
+}
+
+/// 从 `<` 开始的标签的长度，不是标签时返回 None
+fn tag_len(s: &str) -> Option<usize> {
+    let end = |pat: &str| s.find(pat).map(|i| i + pat.len());
+    if s.starts_with("<!--") {
+        return end("-->");
+    }
+    if s.starts_with("<![CDATA[") {
+        return end("]]>");
+    }
+    if s.starts_with("<?") {
+        return end("?>");
+    }
+    let next = s[1..].chars().next()?;
+    let next = match next {
+        '/' => s[2..].chars().next()?,
+        c => c,
+    };
+    if !(next.is_ascii_alphabetic() || next == '!') {
+        return None;
+    }
+    // 属性值中可能出现 `>`，所以要跳过引号中的内容
+    let mut quote = None;
+    for (i, c) in s.char_indices().skip(1) {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn classify(raw: &str, html: bool) -> Token<'_> {
+    if raw.starts_with("<!") || raw.starts_with("<?") {
+        return Token::Special(raw);
+    }
+    let closing = raw.starts_with("</");
+    let inner = &raw[if closing { 2 } else { 1 }..];
+    let name_len = inner
+        .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .unwrap_or(inner.len());
+    let name = &inner[..name_len];
+    match closing {
+        true => Token::Close { name, raw },
+        false => Token::Open {
+            name,
+            raw,
+            closed: raw.ends_with("/>")
+                || (html && VOID_ELEMENTS.iter().any(|e| e.eq_ignore_ascii_case(name))),
+        },
+    }
+}
+
+/// 按元素的嵌套层级重新缩进 XML/HTML，只包含一段文本的元素保持在一行
+pub fn indent_markup(s: &str, html: bool) -> String {
+    let tokens: Vec<_> = tokenize(s, html)
+        .into_iter()
+        .filter(|t| !matches!(t, Token::Text(text) if text.trim().is_empty()))
+        .collect();
+    let same = |a: &str, b: &str| match html {
+        true => a.eq_ignore_ascii_case(b),
+        false => a == b,
+    };
+
+    // 还没有结束的元素。HTML 中 <p>、<li> 这类可以省略结束标签的元素，
+    // 遇到同名的下一个元素或者外层结束时出栈
+    let mut stack: Vec<&str> = Vec::new();
+    let mut lines = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Token::Open { name, .. } = &tokens[i] {
+            let implicit = ["li", "p"].iter().any(|tag| tag.eq_ignore_ascii_case(name));
+            if html && implicit && stack.last().is_some_and(|open| same(open, name)) {
+                stack.pop();
+            }
+        }
+        let pad = "  ".repeat(stack.len());
+        match &tokens[i] {
+            Token::Open {
+                name,
+                raw,
+                closed: false,
+            } => match (tokens.get(i + 1), tokens.get(i + 2)) {
+                (
+                    Some(Token::Close {
+                        name: end,
+                        raw: close,
+                    }),
+                    _,
+                ) if same(name, end) => {
+                    lines.push(format!("{}{}{}", pad, raw, close));
+                    i += 1;
+                }
+                (
+                    Some(Token::Text(text)),
+                    Some(Token::Close {
+                        name: end,
+                        raw: close,
+                    }),
+                ) if same(name, end) && !text.trim().contains('\n') => {
+                    lines.push(format!("{}{}{}{}", pad, raw, text.trim(), close));
+                    i += 2;
+                }
+                _ => {
+                    lines.push(format!("{}{}", pad, raw));
+                    stack.push(name);
+                }
+            },
+            Token::Close { name, raw } => {
+                if let Some(pos) = stack.iter().rposition(|open| same(open, name)) {
+                    stack.truncate(pos);
+                }
+                lines.push(format!("{}{}", "  ".repeat(stack.len()), raw));
+            }
+            Token::Open { raw, .. } | Token::Special(raw) => lines.push(format!("{}{}", pad, raw)),
+            Token::Text(text) => lines.extend(
+                text.trim()
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| format!("{}{}", pad, line.trim())),
+            ),
+        }
+        i += 1;
+    }
+    lines.join("\n")
+}
+
+/// 给 XML/HTML 着色：标签为蓝色，属性名为青色，属性值为绿色，注释为灰色
+pub fn colorize_markup(s: &str, html: bool) -> String {
+    tokenize(s, html)
+        .into_iter()
+        .map(|token| match token {
+            Token::Open { raw, .. } | Token::Close { raw, .. } => colorize_tag(raw),
+            Token::Special(raw) if raw.starts_with("<!--") => raw.bright_black().to_string(),
+            Token::Special(raw) => raw.purple().to_string(),
+            Token::Text(text) => text.to_string(),
+        })
+        .collect()
+}
+
+fn colorize_tag(raw: &str) -> String {
+    let start = if raw.starts_with("</") { 2 } else { 1 };
+    let end = raw.len() - if raw.ends_with("/>") { 2 } else { 1 };
+    let end = end.max(start);
+    let inner = &raw[start..end];
+    let name_len = inner.find(char::is_whitespace).unwrap_or(inner.len());
+
+    let mut out = format!("{}{}", &raw[..start], &inner[..name_len])
+        .blue()
+        .to_string();
+    let mut chars = inner[name_len..].chars().peekable();
+    let mut value = false;
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => out.push(c),
+            '=' => {
+                out.push(c);
+                value = true;
+            }
+            '"' | '\'' => {
+                let mut token = String::from(c);
+                for next in chars.by_ref() {
+                    token.push(next);
+                    if next == c {
+                        break;
+                    }
+                }
+                out.push_str(&token.green().to_string());
+                value = false;
+            }
+            c => {
+                let mut token = String::from(c);
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == '=' {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+                match value {
+                    true => out.push_str(&token.green().to_string()),
+                    false => out.push_str(&token.cyan().to_string()),
+                }
+                value = false;
+            }
+        }
+    }
+    out.push_str(&raw[end..].blue().to_string());
+    out
+}
+
+/// 给 YAML 着色：key 为蓝色，字符串为绿色，数字、布尔和 null 为黄色，注释为灰色
+pub fn colorize_yaml(s: &str) -> String {
+    // 正在处理的多行字符串（`|` 或 `>`）所在行的缩进，缩进更深的行都属于这个字符串
+    let mut block: Option<usize> = None;
+    let mut lines = Vec::new();
+    for line in s.lines() {
+        let indent = line.len() - line.trim_start().len();
+        if let Some(parent) = block {
+            if line.trim().is_empty() || indent > parent {
+                lines.push(line.green().to_string());
+                continue;
+            }
+            block = None;
+        }
+        if matches!(line.trim_end(), "---" | "...") {
+            lines.push(line.purple().to_string());
+            continue;
+        }
+
+        let (code, comment) = split_comment(line);
+        let mut out = String::new();
+        let mut rest = code;
+        // 缩进以及列表项开头的 `- `
+        loop {
+            let trimmed = rest.trim_start();
+            out.push_str(&rest[..rest.len() - trimmed.len()]);
+            rest = trimmed;
+            match rest.starts_with("- ") || rest == "-" {
+                true => {
+                    out.push('-');
+                    rest = &rest[1..];
+                }
+                false => break,
+            }
+        }
+        if let Some(pos) = find_key(rest) {
+            out.push_str(&rest[..pos].blue().to_string());
+            out.push(':');
+            rest = &rest[pos + 1..];
+        }
+
+        let value = rest.trim();
+        let lead = rest.len() - rest.trim_start().len();
+        out.push_str(&rest[..lead]);
+        if value.starts_with('|') || value.starts_with('>') {
+            block = Some(indent);
+            out.push_str(&value.purple().to_string());
+        } else {
+            out.push_str(&colorize_scalar(value));
+        }
+        out.push_str(&rest[lead + value.len()..]);
+        if let Some(comment) = comment {
+            out.push_str(&comment.bright_black().to_string());
+        }
+        lines.push(out);
+    }
+    lines.join("\n")
+}
+
+fn colorize_scalar(v: &str) -> String {
+    let literal = matches!(
+        v,
+        "true" | "True" | "TRUE" | "false" | "False" | "FALSE" | "null" | "Null" | "NULL" | "~"
+    ) || v.parse::<f64>().is_ok();
+    match v.chars().next() {
+        None => String::new(),
+        // 行内的对象和数组不再细分
+        Some('{' | '[') => v.to_string(),
+        // 锚点、别名和类型标签
+        Some('&' | '*' | '!') => v.purple().to_string(),
+        _ if literal => v.yellow().to_string(),
+        _ => v.green().to_string(),
+    }
+}
+
+/// 在引号之外查找满足 `pred` 的字符，引号只有出现在值的开头时才算数
+fn find_unquoted(s: &str, pred: impl Fn(usize, char) -> bool) -> Option<usize> {
+    let mut quote = None;
+    let mut prev = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'')
+                if prev.is_none_or(|p: char| p.is_whitespace() || "[{,:".contains(p)) =>
+            {
+                quote = Some(c)
+            }
+            (Some(q), c) if q == c => quote = None,
+            (None, c) if pred(i, c) => return Some(i),
+            _ => {}
+        }
+        prev = Some(c);
+    }
+    None
+}
+
+/// 拆出行尾的注释：`#` 位于行首或者前面是空白时才是注释
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let pos = find_unquoted(line, |i, c| {
+        c == '#' && line[..i].chars().last().is_none_or(char::is_whitespace)
+    });
+    match pos {
+        Some(pos) => (&line[..pos], Some(&line[pos..])),
+        None => (line, None),
+    }
+}
+
+/// `key: value` 中冒号的位置，冒号后面必须是空白或者行尾
+fn find_key(s: &str) -> Option<usize> {
+    if s.starts_with('{') || s.starts_with('[') {
+        return None;
+    }
+    find_unquoted(s, |i, c| {
+        c == ':' && s[i + 1..].chars().next().is_none_or(char::is_whitespace)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indent_markup_should_nest_elements() {
+        let xml = r#"<?xml version="1.0"?><feed><!-- c --><entry id="1"><title>a &lt; b</title><empty/><link href="/x"></link></entry></feed>"#;
+        assert_eq!(
+            indent_markup(xml, false),
+            r#"<?xml version="1.0"?>
+<feed>
+  <!-- c -->
+  <entry id="1">
+    <title>a &lt; b</title>
+    <empty/>
+    <link href="/x"></link>
+  </entry>
+</feed>"#
+        );
+    }
+
+    #[test]
+    fn indent_markup_should_handle_html() {
+        let html =
+            "<!DOCTYPE html><html><head><meta charset=utf-8><script>if (a<b) {}</script></head>\
+                    <body><ul><li>one<li>two</ul><p>first<p>x < y <br> z</p></body></html>";
+        assert_eq!(
+            indent_markup(html, true),
+            "<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset=utf-8>
+    <script>if (a<b) {}</script>
+  </head>
+  <body>
+    <ul>
+      <li>
+        one
+      <li>
+        two
+    </ul>
+    <p>
+      first
+    <p>
+      x < y
+      <br>
+      z
+    </p>
+  </body>
+</html>"
+        );
+    }
+
+    #[test]
+    fn colorize_should_keep_content() {
+        colored::control::set_override(false);
+        let html = r#"<a href="/x?a>b" data-x=1 hidden>t</a><br/><!-- c -->"#;
+        assert_eq!(colorize_markup(html, true), html);
+        let yaml = "---\nname: 'a: b' # c\nlist:\n  - 1\n  - key: \"x # y\"\ntext: |\n  line: 1\n\n  # line 2\nnext: ~";
+        assert_eq!(colorize_yaml(yaml), yaml);
+    }
+
+    #[test]
+    fn colorize_yaml_should_detect_keys_and_blocks() {
+        assert_eq!(
+            split_comment("a: 'x # y' # z"),
+            ("a: 'x # y' ", Some("# z"))
+        );
+        assert_eq!(
+            split_comment("url: http://x/#top"),
+            ("url: http://x/#top", None)
+        );
+        assert_eq!(find_key("url: http://x"), Some(3));
+        assert_eq!(find_key("\"a: b\": c"), Some(6));
+        assert_eq!(find_key("{a: 1}"), None);
+    }
+}
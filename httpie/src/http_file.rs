@@ -0,0 +1,363 @@
+use crate::filter::Filter;
+use anyhow::{anyhow, Context, Result};
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// 变量引用可以嵌套（变量的值中引用其他变量），超过这个深度时认为出现了循环引用
+const MAX_DEPTH: usize = 16;
+
+/// `.http` 文件中的一个请求，url、请求头和 body 中可以引用 `{{变量}}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+/// `###` 分隔出来的一段：其中定义的变量，以及最多一个请求
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Block {
+    /// `###` 之后的说明文字
+    pub title: Option<String>,
+    /// `# @name login`，之后的请求可以通过 `{{login.response.body.$.token}}` 引用它的响应
+    pub name: Option<String>,
+    pub variables: Vec<(String, String)>,
+    pub request: Option<FileRequest>,
+}
+
+/// 解析 VS Code REST Client 格式的 `.http` 文件，`< path` 形式的 body 相对于 dir 读取
+pub fn parse(content: &str, dir: &Path) -> Result<Vec<Block>> {
+    let mut blocks = vec![Block::default()];
+    let mut lines: Vec<&str> = Vec::new();
+    for line in content.lines() {
+        if let Some(title) = line.strip_prefix("###") {
+            parse_block(blocks.last_mut().unwrap(), &lines, dir)?;
+            lines.clear();
+            let title = title.trim();
+            blocks.push(Block {
+                title: (!title.is_empty()).then(|| title.to_string()),
+                ..Default::default()
+            });
+        } else {
+            lines.push(line);
+        }
+    }
+    parse_block(blocks.last_mut().unwrap(), &lines, dir)?;
+    Ok(blocks)
+}
+
+fn parse_block(block: &mut Block, lines: &[&str], dir: &Path) -> Result<()> {
+    let mut lines = lines.iter().map(|l| l.trim_end()).peekable();
+
+    // 请求行之前是变量定义和注释
+    let request_line = loop {
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        let trimmed = line.trim();
+        if let Some(comment) = trimmed
+            .strip_prefix('#')
+            .or_else(|| trimmed.strip_prefix("//"))
+        {
+            if let Some(name) = comment.trim().strip_prefix("@name") {
+                block.name = Some(name.trim().to_string());
+            }
+        } else if let Some(var) = trimmed.strip_prefix('@') {
+            let (k, v) = var
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid variable definition {}", line))?;
+            block.variables.push((k.trim().into(), v.trim().into()));
+        } else if !trimmed.is_empty() {
+            break trimmed;
+        }
+    };
+
+    // `GET url HTTP/1.1`，方法可以省略，默认为 GET
+    let mut parts = request_line.split_whitespace();
+    let (method, mut url) = match (parts.next(), parts.next()) {
+        (Some(method), Some(url)) if method.chars().all(|c| c.is_ascii_uppercase()) => {
+            (method.to_string(), url.to_string())
+        }
+        _ => ("GET".to_string(), request_line.to_string()),
+    };
+    // 以 ? 或 & 开头的行是 query string 的延续
+    while let Some(query) = lines.next_if(|l| l.trim_start().starts_with(['?', '&'])) {
+        url.push_str(query.trim());
+    }
+
+    let mut headers = Vec::new();
+    for line in lines.by_ref() {
+        if line.trim().is_empty() {
+            break;
+        }
+        if line.trim_start().starts_with('#') || line.trim_start().starts_with("//") {
+            continue;
+        }
+        let (k, v) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid header {}", line))?;
+        headers.push((k.trim().to_string(), v.trim().to_string()));
+    }
+
+    let body = lines.collect::<Vec<_>>().join("\n");
+    let body = match body.trim() {
+        "" => None,
+        // `< ./data.json` 表示从文件中读取 body
+        trimmed => match trimmed.strip_prefix("< ") {
+            Some(path) => {
+                let path = dir.join(path.trim());
+                Some(
+                    fs::read_to_string(&path)
+                        .with_context(|| format!("Failed to read {}", path.display()))?,
+                )
+            }
+            None => Some(body.trim_end().to_string()),
+        },
+    };
+
+    block.request = Some(FileRequest {
+        method,
+        url,
+        headers,
+        body,
+    });
+    Ok(())
+}
+
+/// 命名请求的响应，供后面的请求引用
+#[derive(Debug, Clone)]
+struct Captured {
+    headers: HeaderMap,
+    body: String,
+}
+
+/// 变量：环境文件中的变量、`.http` 文件中的变量以及命名请求的响应
+#[derive(Debug, Default)]
+pub struct Variables {
+    env: HashMap<String, String>,
+    file: HashMap<String, String>,
+    responses: HashMap<String, Captured>,
+}
+
+impl Variables {
+    /// 读取 `KEY=VALUE` 格式的环境文件，`#` 开头的行是注释
+    pub fn load_env(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read env file {}", path.display()))?;
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (k, v) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid line in env file {}: {}", path.display(), line))?;
+            let v = v.trim();
+            let v = v
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| v.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(v);
+            self.env.insert(k.trim().to_string(), v.to_string());
+        }
+        Ok(())
+    }
+
+    /// 和 REST Client 一样，文件中定义的变量优先于环境文件中的变量
+    pub fn define(&mut self, variables: &[(String, String)]) {
+        self.file.extend(variables.iter().cloned());
+    }
+
+    pub fn capture(&mut self, name: &str, headers: HeaderMap, body: String) {
+        self.responses
+            .insert(name.to_string(), Captured { headers, body });
+    }
+
+    /// 替换请求中所有的变量引用
+    pub fn resolve_request(&self, req: &FileRequest) -> Result<FileRequest> {
+        Ok(FileRequest {
+            method: req.method.clone(),
+            url: self.resolve(&req.url)?,
+            headers: req
+                .headers
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), self.resolve(v)?)))
+                .collect::<Result<_>>()?,
+            body: req.body.as_deref().map(|b| self.resolve(b)).transpose()?,
+        })
+    }
+
+    /// 替换字符串中的 `{{name}}`
+    pub fn resolve(&self, s: &str) -> Result<String> {
+        self.resolve_depth(s, 0)
+    }
+
+    fn resolve_depth(&self, s: &str, depth: usize) -> Result<String> {
+        if depth > MAX_DEPTH {
+            return Err(anyhow!("Variable reference is too deep, is there a cycle?"));
+        }
+        let mut out = String::new();
+        let mut rest = s;
+        while let Some(start) = rest.find("{{") {
+            let end = rest[start..]
+                .find("}}")
+                .ok_or_else(|| anyhow!("Unterminated variable reference in {}", s))?;
+            out.push_str(&rest[..start]);
+            let name = rest[start + 2..start + end].trim();
+            out.push_str(&self.lookup(name, depth)?);
+            rest = &rest[start + end + 2..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    fn lookup(&self, name: &str, depth: usize) -> Result<String> {
+        if let Some(var) = name.strip_prefix("$processEnv ") {
+            return std::env::var(var.trim())
+                .with_context(|| format!("Environment variable {} is not set", var.trim()));
+        }
+        if name == "$timestamp" {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+            return Ok(now.as_secs().to_string());
+        }
+        if let Some((request, path)) = name.split_once(".response.") {
+            let captured = self
+                .responses
+                .get(request)
+                .ok_or_else(|| anyhow!("Request {} has no response yet", request))?;
+            return captured.lookup(path);
+        }
+        let value = self
+            .file
+            .get(name)
+            .or_else(|| self.env.get(name))
+            .ok_or_else(|| anyhow!("Unknown variable {{{{{}}}}}", name))?;
+        self.resolve_depth(value, depth + 1)
+    }
+}
+
+impl Captured {
+    /// `body.$.path` 用 --filter 的语法取 JSON 中的值，`body.*` 为整个 body；`headers.Name` 取响应头
+    fn lookup(&self, path: &str) -> Result<String> {
+        if let Some(name) = path.strip_prefix("headers.") {
+            return self
+                .headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(String::from)
+                .ok_or_else(|| anyhow!("Missing response header {}", name));
+        }
+        let path = path
+            .strip_prefix("body.")
+            .ok_or_else(|| anyhow!("Invalid response reference {}", path))?;
+        if path == "*" {
+            return Ok(self.body.clone());
+        }
+        let filter: Filter = path.parse()?;
+        let body: Value = serde_json::from_str(&self.body)
+            .with_context(|| format!("Response body is not JSON, cannot evaluate {}", path))?;
+        Ok(match filter.apply(&body) {
+            Value::String(s) => s,
+            value => value.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"@host = http://localhost:3000
+@user = alice
+
+### Login
+# @name login
+POST {{host}}/login
+Content-Type: application/json
+
+{"user": "{{user}}"}
+
+###
+GET {{host}}/me
+    ?verbose=1
+Authorization: Bearer {{login.response.body.$.token}}
+X-Request-Id: {{login.response.headers.x-request-id}}
+"#;
+
+    #[test]
+    fn parse_should_split_blocks() {
+        let blocks = parse(FILE, Path::new(".")).unwrap();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0].variables.len(), 2);
+        assert!(blocks[0].request.is_none());
+
+        assert_eq!(blocks[1].name.as_deref(), Some("login"));
+        assert_eq!(blocks[1].title.as_deref(), Some("Login"));
+        let login = blocks[1].request.as_ref().unwrap();
+        assert_eq!(login.method, "POST");
+        assert_eq!(login.url, "{{host}}/login");
+        assert_eq!(login.body.as_deref(), Some("{\"user\": \"{{user}}\"}"));
+
+        let me = blocks[2].request.as_ref().unwrap();
+        assert_eq!(me.url, "{{host}}/me?verbose=1");
+        assert_eq!(me.headers.len(), 2);
+        assert_eq!(me.body, None);
+    }
+
+    #[test]
+    fn variables_should_resolve_file_env_and_responses() {
+        let blocks = parse(FILE, Path::new(".")).unwrap();
+        let mut vars = Variables::default();
+        vars.env.insert("user".into(), "bob".into());
+        vars.env.insert("base".into(), "{{host}}/v1".into());
+        vars.define(&blocks[0].variables);
+
+        let login = vars
+            .resolve_request(blocks[1].request.as_ref().unwrap())
+            .unwrap();
+        assert_eq!(login.url, "http://localhost:3000/login");
+        assert_eq!(login.body.as_deref(), Some("{\"user\": \"alice\"}"));
+        assert_eq!(
+            vars.resolve("{{base}}").unwrap(),
+            "http://localhost:3000/v1"
+        );
+
+        let me = blocks[2].request.as_ref().unwrap();
+        assert!(vars.resolve_request(me).is_err());
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", "42".parse().unwrap());
+        vars.capture("login", headers, r#"{"token": "t0k"}"#.into());
+        let me = vars.resolve_request(me).unwrap();
+        assert_eq!(
+            me.headers,
+            vec![
+                ("Authorization".into(), "Bearer t0k".into()),
+                ("X-Request-Id".into(), "42".into())
+            ]
+        );
+
+        assert!(vars.resolve("{{missing}}").is_err());
+        vars.define(&[("a".into(), "{{b}}".into()), ("b".into(), "{{a}}".into())]);
+        assert!(vars.resolve("{{a}}").is_err());
+    }
+
+    #[test]
+    fn load_env_should_parse_dotenv() {
+        let path = std::env::temp_dir().join("httpie-http-file-test.env");
+        fs::write(&path, "# dev\nexport host=\"http://dev\"\ntoken = 'abc'\n").unwrap();
+        let mut vars = Variables::default();
+        vars.load_env(&path).unwrap();
+        assert_eq!(
+            vars.resolve("{{host}}/{{token}}").unwrap(),
+            "http://dev/abc"
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,446 @@
+use anyhow::{anyhow, Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::{Map, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use url::form_urlencoded;
+
+/// 命令行中的 request item，语法和 httpie 保持一致：
+///
+/// - `Header:value`       请求头
+/// - `param==value`       URL query string
+/// - `field=value`        字符串类型的 body 字段
+/// - `field=@path`        从文件中读取字符串类型的 body 字段
+/// - `field:=json`        原始 JSON 类型的 body 字段（数字、布尔、嵌套对象等）
+/// - `field:=@path`       从文件中读取原始 JSON 类型的 body 字段
+/// - `field@path`         上传文件
+#[derive(Debug, PartialEq, Clone)]
+pub enum RequestItem {
+    Header(String, String),
+    Query(String, String),
+    Data(String, String),
+    DataFile(String, PathBuf),
+    Json(String, Value),
+    JsonFile(String, PathBuf),
+    File(String, PathBuf),
+}
+
+/// body 的序列化方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BodyMode {
+    Json,
+    Form,
+    Multipart,
+}
+
+/// 序列化好的请求 body，以及对应的 Content-Type
+#[derive(Debug, Clone, PartialEq)]
+pub struct Body {
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// 分隔符按长度从长到短排列，同一位置上优先匹配更长的分隔符
+const SEPARATORS: [&str; 7] = [":=@", "==", ":=", "=@", "=", "@", ":"];
+
+impl FromStr for RequestItem {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || anyhow!("Failed to parse request item {}", s);
+
+        // 从左往右找到第一个没有被 `\` 转义的分隔符，分隔符左边是 key，右边是 value
+        let mut key = String::new();
+        let mut chars = s.char_indices();
+        while let Some((i, c)) = chars.next() {
+            if c == '\\' {
+                if let Some((_, next)) = chars.next() {
+                    key.push(next);
+                }
+                continue;
+            }
+            let rest = &s[i..];
+            if let Some(sep) = SEPARATORS.iter().find(|sep| rest.starts_with(**sep)) {
+                if key.is_empty() {
+                    return Err(err());
+                }
+                let value = &rest[sep.len()..];
+                return Ok(match *sep {
+                    ":" => Self::Header(key, value.trim().to_string()),
+                    "==" => Self::Query(key, value.to_string()),
+                    "=" => Self::Data(key, value.to_string()),
+                    "=@" => Self::DataFile(key, value.into()),
+                    ":=" => Self::Json(
+                        key,
+                        serde_json::from_str(value)
+                            .with_context(|| format!("Invalid JSON in request item {}", s))?,
+                    ),
+                    ":=@" => Self::JsonFile(key, value.into()),
+                    _ => Self::File(key, value.into()),
+                });
+            }
+            key.push(c);
+        }
+
+        Err(err())
+    }
+}
+
+/// 因为我们为 RequestItem 实现了 FromStr， 这里可以直接 s.parse() 得到 RequestItem
+pub fn parse_request_item(s: &str) -> Result<RequestItem> {
+    s.parse()
+}
+
+/// 从 request items 中取出所有的请求头
+pub fn headers(items: &[RequestItem]) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for item in items {
+        if let RequestItem::Header(k, v) = item {
+            headers.append(HeaderName::from_str(k)?, HeaderValue::from_str(v)?);
+        }
+    }
+    Ok(headers)
+}
+
+/// 从 request items 中取出所有的 query 参数
+pub fn query(items: &[RequestItem]) -> Vec<(&str, &str)> {
+    items
+        .iter()
+        .filter_map(|item| match item {
+            RequestItem::Query(k, v) => Some((k.as_str(), v.as_str())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 把 body 相关的 request items 组装成 JSON 对象，如果没有 body 字段则返回 None
+pub fn json_body(items: &[RequestItem]) -> Result<Option<Value>> {
+    let mut body = Map::new();
+    for item in items {
+        let (k, v) = match item {
+            RequestItem::Data(k, v) => (k, Value::String(v.clone())),
+            RequestItem::DataFile(k, path) => (k, Value::String(read_file(path)?)),
+            RequestItem::Json(k, v) => (k, v.clone()),
+            RequestItem::JsonFile(k, path) => (
+                k,
+                serde_json::from_str(&read_file(path)?)
+                    .with_context(|| format!("Invalid JSON in {}", path.display()))?,
+            ),
+            RequestItem::File(k, _) => {
+                return Err(anyhow!(
+                    "Invalid file field {} (perhaps you meant --form?)",
+                    k
+                ))
+            }
+            _ => continue,
+        };
+        body.insert(k.clone(), v);
+    }
+
+    Ok(if body.is_empty() {
+        None
+    } else {
+        Some(Value::Object(body))
+    })
+}
+
+/// 按照 mode 把 body 相关的 request items 序列化，有文件字段时自动切换到 multipart
+pub fn body(items: &[RequestItem], mode: BodyMode) -> Result<Option<Body>> {
+    let has_file = items.iter().any(|i| matches!(i, RequestItem::File(..)));
+    let mode = if has_file { BodyMode::Multipart } else { mode };
+
+    match mode {
+        BodyMode::Json => Ok(json_body(items)?.map(|v| Body {
+            content_type: mime::APPLICATION_JSON.to_string(),
+            data: v.to_string().into_bytes(),
+        })),
+        BodyMode::Form => {
+            let fields = form_fields(items)?;
+            if fields.is_empty() {
+                return Ok(None);
+            }
+            let data = form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(fields)
+                .finish();
+            Ok(Some(Body {
+                content_type: mime::APPLICATION_WWW_FORM_URLENCODED.to_string(),
+                data: data.into_bytes(),
+            }))
+        }
+        BodyMode::Multipart => {
+            let has_fields = items.iter().any(|item| {
+                matches!(
+                    item,
+                    RequestItem::Data(..)
+                        | RequestItem::DataFile(..)
+                        | RequestItem::Json(..)
+                        | RequestItem::JsonFile(..)
+                        | RequestItem::File(..)
+                )
+            });
+            if !has_fields {
+                return Ok(None);
+            }
+            multipart_body(items, &boundary()).map(Some)
+        }
+    }
+}
+
+/// 从 stdin 读到的原始 body，没有指定 Content-Type 时根据内容猜测：
+/// 合法的 JSON 为 application/json，其他 UTF-8 文本为 text/plain
+pub fn raw_body(data: Vec<u8>, content_type: Option<&str>) -> Body {
+    let content_type = match content_type {
+        Some(content_type) => content_type.to_string(),
+        None if serde_json::from_slice::<Value>(&data).is_ok() => {
+            mime::APPLICATION_JSON.to_string()
+        }
+        None if std::str::from_utf8(&data).is_ok() => mime::TEXT_PLAIN_UTF_8.to_string(),
+        None => mime::APPLICATION_OCTET_STREAM.to_string(),
+    };
+    Body { content_type, data }
+}
+
+/// form 模式下所有字段都是字符串，`:=` 的 JSON 值会被序列化成字符串
+fn form_fields(items: &[RequestItem]) -> Result<Vec<(String, String)>> {
+    let mut fields = Vec::new();
+    for item in items {
+        let (k, v) = match item {
+            RequestItem::Data(k, v) => (k, v.clone()),
+            RequestItem::DataFile(k, path) => (k, read_file(path)?),
+            RequestItem::Json(k, v) => (k, json_to_field(v)),
+            RequestItem::JsonFile(k, path) => (k, read_file(path)?),
+            _ => continue,
+        };
+        fields.push((k.clone(), v));
+    }
+    Ok(fields)
+}
+
+fn json_to_field(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+/// 按照 RFC 7578 组装 multipart/form-data body
+fn multipart_body(items: &[RequestItem], boundary: &str) -> Result<Body> {
+    let mut data = Vec::new();
+    for (k, v) in form_fields(items)? {
+        data.extend(format!("--{}\r\n", boundary).as_bytes());
+        data.extend(
+            format!(
+                "Content-Disposition: form-data; name=\"{}\"\r\n\r\n",
+                escape_quoted(&k)
+            )
+            .as_bytes(),
+        );
+        data.extend(v.as_bytes());
+        data.extend(b"\r\n");
+    }
+    for item in items {
+        if let RequestItem::File(k, path) = item {
+            let filename = path
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let content_type = mime_guess::from_path(path).first_or_octet_stream();
+            let content =
+                fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            data.extend(format!("--{}\r\n", boundary).as_bytes());
+            data.extend(
+                format!(
+                    "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n",
+                    escape_quoted(k),
+                    escape_quoted(&filename)
+                )
+                .as_bytes(),
+            );
+            data.extend(format!("Content-Type: {}\r\n\r\n", content_type).as_bytes());
+            data.extend(content);
+            data.extend(b"\r\n");
+        }
+    }
+    data.extend(format!("--{}--\r\n", boundary).as_bytes());
+
+    Ok(Body {
+        content_type: format!("{}; boundary={}", mime::MULTIPART_FORM_DATA, boundary),
+        data,
+    })
+}
+
+fn escape_quoted(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// 用当前时间生成 boundary，足以避免和 body 内容冲突
+fn boundary() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!("------------------------{:x}", nanos)
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(s: &str) -> RequestItem {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_request_item_should_work() {
+        assert_eq!(
+            parse("Accept:application/json"),
+            RequestItem::Header("Accept".into(), "application/json".into())
+        );
+        assert_eq!(
+            parse("q==rust"),
+            RequestItem::Query("q".into(), "rust".into())
+        );
+        assert_eq!(
+            parse("name=tyr"),
+            RequestItem::Data("name".into(), "tyr".into())
+        );
+        assert_eq!(
+            parse("bio=@bio.txt"),
+            RequestItem::DataFile("bio".into(), "bio.txt".into())
+        );
+        assert_eq!(
+            parse("meta:={\"a\":[1,true]}"),
+            RequestItem::Json("meta".into(), json!({"a": [1, true]}))
+        );
+        assert_eq!(
+            parse("meta:=@meta.json"),
+            RequestItem::JsonFile("meta".into(), "meta.json".into())
+        );
+        assert_eq!(
+            parse("avatar@logo.png"),
+            RequestItem::File("avatar".into(), "logo.png".into())
+        );
+    }
+
+    #[test]
+    fn parse_request_item_should_split_on_first_separator() {
+        assert_eq!(parse("a=b=c"), RequestItem::Data("a".into(), "b=c".into()));
+        assert_eq!(
+            parse("Referer:http://localhost:3000/?a=1"),
+            RequestItem::Header("Referer".into(), "http://localhost:3000/?a=1".into())
+        );
+        assert_eq!(
+            parse("next==/login?x=1"),
+            RequestItem::Query("next".into(), "/login?x=1".into())
+        );
+    }
+
+    #[test]
+    fn parse_request_item_should_support_escape() {
+        assert_eq!(
+            parse("a\\:b=c"),
+            RequestItem::Data("a:b".into(), "c".into())
+        );
+        assert_eq!(
+            parse("email\\@home=me@example.com"),
+            RequestItem::Data("email@home".into(), "me@example.com".into())
+        );
+    }
+
+    #[test]
+    fn parse_invalid_request_item_should_fail() {
+        assert!("foo".parse::<RequestItem>().is_err());
+        assert!("=bar".parse::<RequestItem>().is_err());
+        assert!("count:=abc".parse::<RequestItem>().is_err());
+    }
+
+    #[test]
+    fn items_should_be_split_into_headers_query_and_body() {
+        let items = vec![
+            parse("X-Api-Key:secret"),
+            parse("page==2"),
+            parse("name=tyr"),
+            parse("age:=18"),
+        ];
+        assert_eq!(headers(&items).unwrap()["x-api-key"], "secret");
+        assert_eq!(query(&items), vec![("page", "2")]);
+        assert_eq!(
+            json_body(&items).unwrap(),
+            Some(json!({"name": "tyr", "age": 18}))
+        );
+        assert_eq!(json_body(&items[..2]).unwrap(), None);
+    }
+
+    #[test]
+    fn json_body_should_reject_file_fields() {
+        let items = vec![parse("avatar@logo.png")];
+        assert!(json_body(&items).is_err());
+    }
+
+    #[test]
+    fn form_body_should_be_urlencoded() {
+        let items = vec![parse("name=tyr chen"), parse("age:=18"), parse("q==1")];
+        let form = body(&items, BodyMode::Form).unwrap().unwrap();
+        assert_eq!(form.content_type, "application/x-www-form-urlencoded");
+        assert_eq!(form.data, b"name=tyr+chen&age=18");
+        assert_eq!(body(&items[2..], BodyMode::Form).unwrap(), None);
+    }
+
+    #[test]
+    fn multipart_body_should_include_fields_and_files() {
+        let path = std::env::temp_dir().join("httpie-multipart-test.txt");
+        fs::write(&path, "hello").unwrap();
+        let items = vec![
+            parse("name=tyr"),
+            RequestItem::File("doc".into(), path.clone()),
+        ];
+        let body = multipart_body(&items, "xyz").unwrap();
+        assert_eq!(body.content_type, "multipart/form-data; boundary=xyz");
+        assert_eq!(
+            String::from_utf8(body.data).unwrap(),
+            "--xyz\r\n\
+             Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+             tyr\r\n\
+             --xyz\r\n\
+             Content-Disposition: form-data; name=\"doc\"; filename=\"httpie-multipart-test.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             hello\r\n\
+             --xyz--\r\n"
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn file_item_should_switch_to_multipart() {
+        let path = std::env::temp_dir().join("httpie-switch-test.bin");
+        fs::write(&path, [0u8, 1, 2]).unwrap();
+        let items = vec![RequestItem::File("f".into(), path.clone())];
+        let multipart = body(&items, BodyMode::Json).unwrap().unwrap();
+        assert!(multipart.content_type.starts_with("multipart/form-data"));
+        fs::remove_file(path).unwrap();
+        // 只有请求头和 query 参数时没有 body，这样 --multipart 也能从 stdin 读取 body
+        let items = vec![parse("X-A:1"), parse("q==1")];
+        assert_eq!(body(&items, BodyMode::Multipart).unwrap(), None);
+    }
+
+    #[test]
+    fn raw_body_should_sniff_content_type() {
+        let content_type = |data: &[u8]| raw_body(data.to_vec(), None).content_type;
+        assert_eq!(content_type(b" {\"a\": 1}\n"), "application/json");
+        assert_eq!(content_type(b"hello"), "text/plain; charset=utf-8");
+        assert_eq!(content_type(&[0xff, 0xfe]), "application/octet-stream");
+        assert_eq!(
+            raw_body(b"{}".to_vec(), Some("application/vnd.api+json")).content_type,
+            "application/vnd.api+json"
+        );
+    }
+}
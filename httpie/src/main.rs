@@ -1,51 +1,284 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use clap::Parser;
-use reqwest::{header, Client, Response, Url};
-use std::collections::HashMap;
-use std::panic::resume_unwind;
-use std::{collections::hash_map, str::FromStr};
+use reqwest::{
+    cookie::CookieStore,
+    header::{self, HeaderMap},
+    Client, Method, RequestBuilder, Response, StatusCode, Url, Version,
+};
+use std::{
+    ffi::OsString,
+    fs,
+    io::{IsTerminal, Read},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
 
+mod auth;
+mod client;
+mod config;
+mod curl;
+mod download;
+mod exit;
+mod expect;
+mod filter;
+mod graphql;
+mod highlight;
+mod http_file;
+mod items;
+mod meta;
+mod printer;
+mod redirect;
+mod retry;
+mod session;
+mod shorthand;
+mod sse;
+mod ws;
+use auth::{Auth, AuthType};
+use client::{ConnectionArgs, Verify};
+use config::Config;
+use download::Download;
+use exit::ExitStatus;
+use expect::{
+    parse_header_expect, parse_json_expect, parse_status_pattern, Expect, HeaderExpect, JsonExpect,
+    StatusPattern,
+};
+use filter::{parse_filter, Filter};
+use http_file::Variables;
+use items::{parse_request_item, Body, BodyMode, RequestItem};
+use meta::Meta;
+use printer::{body_text, get_content_type, parse_print, Pretty, Print, Printer};
+use redirect::{Chain, Redirect};
+use retry::Retry;
+use session::Session;
+use shorthand::expand_url;
+use tokio_tungstenite::Connector;
+
+// 配置文件中的默认选项会出现在命令行参数之前，允许同一个选项出现多次，以最后一次为准
 #[derive(Parser, Debug)]
+#[command(args_override_self = true)]
 struct Opts {
+    /// What to print: H = request headers, B = request body, h = response headers
+    /// (with status line), b = response body, m = metadata. Defaults to hb, or b
+    /// when stdout is not a TTY
+    #[arg(long, global = true, value_parser = parse_print)]
+    print: Option<Print>,
+
+    /// Print the whole request as well as the response, same as --print=HBhb
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Also print elapsed time, remote address and redirect count. The negotiated
+    /// TLS version is not reported, as the HTTP client does not expose it
+    #[arg(long, global = true)]
+    meta: bool,
+
+    /// Output style. Defaults to all, or none when stdout is not a TTY
+    #[arg(long, global = true, value_enum)]
+    pretty: Option<Pretty>,
+
+    /// Only print the part of a JSON response body selected by a JSONPath-like
+    /// expression, e.g. `data.items[*].name`, `items[-1]` or `items[1:3]`
+    #[arg(long, global = true, value_parser = parse_filter)]
+    filter: Option<Filter>,
+
+    /// Serialize data items as application/x-www-form-urlencoded instead of JSON
+    #[arg(short, long, global = true, conflicts_with = "multipart")]
+    form: bool,
+
+    /// Serialize data items as multipart/form-data. File items (field@path)
+    /// always switch to multipart
+    #[arg(long, global = true)]
+    multipart: bool,
+
+    /// Credentials: user:pass for basic/digest (prompted when the password
+    /// is omitted), or the token for bearer
+    #[arg(short, long, global = true)]
+    auth: Option<String>,
+
+    /// How to send the --auth credentials
+    #[arg(long, global = true, value_enum, default_value = "basic")]
+    auth_type: AuthType,
+
+    /// Create, or reuse and update a session. A name is stored per host under
+    /// the config directory, a path (ending with .json) is used as is
+    #[arg(long, global = true, conflicts_with = "session_read_only")]
+    session: Option<String>,
+
+    /// Reuse a session without updating it with the request and response
+    #[arg(long, global = true, value_name = "SESSION")]
+    session_read_only: Option<String>,
+
+    /// Download the response body to a file instead of printing it
+    #[arg(short, long, global = true)]
+    download: bool,
+
+    /// Save the downloaded body to this file. Defaults to the name from
+    /// Content-Disposition or the URL path
+    #[arg(short, long, global = true, requires = "download")]
+    output: Option<PathBuf>,
+
+    /// Resume a partial download of --output with a Range request
+    #[arg(short = 'c', long = "continue", global = true, requires = "download")]
+    resume: bool,
+
+    /// Print the response body as it arrives. text/event-stream responses
+    /// are parsed into events, with JSON data pretty-printed
+    #[arg(short = 'S', long, global = true, conflicts_with = "download")]
+    stream: bool,
+
+    /// Never read the request body from stdin, even when it is not a TTY
+    #[arg(long, global = true)]
+    ignore_stdin: bool,
+
+    /// Send DATA as the request body as is, like piping it to stdin
+    #[arg(long, global = true, value_name = "DATA")]
+    raw: Option<String>,
+
+    /// Content-Type of the body from stdin or --raw. Defaults to
+    /// application/json for valid JSON and text/plain for other text
+    #[arg(long, global = true)]
+    content_type: Option<String>,
+
+    /// Build the request and print it instead of sending it
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Print the request as an equivalent curl command instead of sending it
+    #[arg(long, global = true, conflicts_with = "offline")]
+    to_curl: bool,
+
+    /// Exit with a non-zero status on 3xx (unless --follow), 4xx and 5xx
+    /// responses: 3, 4 and 5 respectively
+    #[arg(long, global = true)]
+    check_status: bool,
+
+    /// Fail unless the response status matches one of these codes or
+    /// classes, e.g. `200,201` or `2xx`. With run, every response is checked
+    #[arg(long, global = true, value_delimiter = ',', value_name = "STATUS", value_parser = parse_status_pattern)]
+    expect_status: Vec<StatusPattern>,
+
+    /// Fail unless the response header matches the regex, e.g.
+    /// `Content-Type:^application/json`. Can be repeated
+    #[arg(long, global = true, value_name = "NAME:REGEX", value_parser = parse_header_expect)]
+    expect_header: Vec<HeaderExpect>,
+
+    /// Fail unless the value at the JSON path (same syntax as --filter)
+    /// equals the JSON value, e.g. `data.items[0].id=1`. Can be repeated
+    #[arg(long, global = true, value_name = "PATH=VALUE", value_parser = parse_json_expect, conflicts_with_all = ["download", "stream"])]
+    expect_json: Vec<JsonExpect>,
+
+    /// Follow 30x Location redirects
+    #[arg(short = 'F', long, global = true)]
+    follow: bool,
+
+    /// Maximum number of redirects to follow with --follow
+    #[arg(long, global = true, default_value_t = 30)]
+    max_redirects: usize,
+
+    /// Print every intermediate response (and request with --verbose) in the
+    /// redirect chain, not only the last one
+    #[arg(long, global = true, requires = "follow")]
+    all: bool,
+
+    /// Give up when a request (including reading the response body) takes
+    /// longer than this many seconds
+    #[arg(long, global = true, value_name = "SECONDS")]
+    timeout: Option<f64>,
+
+    /// Retry a failed request up to N times with exponential backoff, on
+    /// timeouts, connection errors and --retry-on statuses
+    #[arg(long, global = true, value_name = "N", default_value_t = 0)]
+    retries: usize,
+
+    /// Response statuses that trigger a retry
+    #[arg(long, global = true, value_delimiter = ',', default_value = "502,503,504", value_parser = parse_status)]
+    retry_on: Vec<StatusCode>,
+
+    /// Also retry non-idempotent methods such as POST and PATCH
+    #[arg(long, global = true)]
+    retry_all_methods: bool,
+
+    #[command(flatten)]
+    conn: ConnectionArgs,
+
+    /// Scheme used when the URL has none, e.g. `example.com` or `:3000/api`
+    #[arg(long, global = true, default_value = "http")]
+    default_scheme: String,
+
     #[clap(subcommand)]
     subcmd: SubCommand,
 }
 
-// 子命令分别对应不同的 HTTP 方法， 目前只支持 get / post
+impl Opts {
+    fn body_mode(&self) -> BodyMode {
+        match (self.form, self.multipart) {
+            (_, true) => BodyMode::Multipart,
+            (true, _) => BodyMode::Form,
+            _ => BodyMode::Json,
+        }
+    }
+}
+
+// 子命令分别对应不同的 HTTP 方法，其他自定义方法可以通过 request 子命令发送
 #[derive(Parser, Debug)]
 enum SubCommand {
     Get(Get),
     Post(Post),
-    // 我们暂时不支持其他 HTTP 方法
+    /// feed put with an url and optional request items. We will put
+    /// as JSON, and retrieve the response for you.
+    Put(Post),
+    /// feed patch with an url and optional request items. We will patch
+    /// as JSON, and retrieve the response for you.
+    Patch(Post),
+    /// feed delete with an url and optional request items.
+    Delete(Post),
+    /// feed head with an url and we will retrieve the response headers for you
+    Head(Post),
+    /// feed options with an url and we will retrieve the response for you
+    Options(Post),
+    Request(Request),
+    FromCurl(FromCurl),
+    Run(Run),
+    /// open a WebSocket connection to the url, print incoming messages and
+    /// send each line read from stdin as a text message
+    Ws(Get),
+    Graphql(Graphql),
 }
 
-/// 命令行中的 key=value 可以通过 parse_kv_pair 解析成 KvPair 结构
-#[derive(Debug, PartialEq, Clone)]
-struct KvPair {
-    k: String,
-    v: String,
-}
-
-impl FromStr for KvPair {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        // 使用 = 进行split，这会得到一个迭代器
-        let mut split = s.split("=");
-        let err = || anyhow!(format!("Failed to parse {}", s));
-        Ok(Self {
-            // 从迭代器中取第一个结果作为 key， 迭代器返回 some(T)/None
-            // 我们将其转换成 Ok(T)/Err(E), 然后用 ？ 处理错误
-            k: (split.next().ok_or_else(err)?).to_string(),
-            // 从迭代器中取第二个结果为 value
-            v: (split.next().ok_or_else(err)?).to_string(),
-        })
+impl SubCommand {
+    /// 子命令对应的 URL 和 request items，run 子命令中每个请求的 URL 都不同，返回 None
+    fn target(&self) -> Option<(&str, &[RequestItem])> {
+        match self {
+            SubCommand::Get(args) | SubCommand::Ws(args) => Some((&args.url, &args.items)),
+            SubCommand::Post(args)
+            | SubCommand::Put(args)
+            | SubCommand::Patch(args)
+            | SubCommand::Delete(args)
+            | SubCommand::Head(args)
+            | SubCommand::Options(args) => Some((&args.url, &args.items)),
+            SubCommand::Request(args) => Some((&args.args.url, &args.args.items)),
+            SubCommand::Graphql(args) => Some((&args.url, &args.items)),
+            SubCommand::FromCurl(_) => unreachable!("from-curl is translated by parse_opts"),
+            SubCommand::Run(_) => None,
+        }
     }
-}
 
-/// 因为我们为 KvPair 实现了 FormStr， 这里可以直接 s.parse() 得到 KvPair
-fn parse_kv_pair(s: &str) -> Result<KvPair> {
-    Ok(s.parse()?)
+    fn url_mut(&mut self) -> Option<&mut String> {
+        match self {
+            SubCommand::Get(args) | SubCommand::Ws(args) => Some(&mut args.url),
+            SubCommand::Post(args)
+            | SubCommand::Put(args)
+            | SubCommand::Patch(args)
+            | SubCommand::Delete(args)
+            | SubCommand::Head(args)
+            | SubCommand::Options(args) => Some(&mut args.url),
+            SubCommand::Request(args) => Some(&mut args.args.url),
+            SubCommand::Graphql(args) => Some(&mut args.url),
+            SubCommand::FromCurl(_) => unreachable!("from-curl is translated by parse_opts"),
+            SubCommand::Run(_) => None,
+        }
+    }
 }
 
 // get 子命令
@@ -55,9 +288,13 @@ struct Get {
     /// HTTP get URL
     #[arg(value_parser = parse_url)]
     url: String,
+
+    /// Request items: Header:value, param==value, field=value, field:=json
+    #[arg(value_parser = parse_request_item)]
+    items: Vec<RequestItem>,
 }
 
-/// feed post with an url and optional key=value pairs. We will post
+/// feed post with an url and optional request items. We will post
 /// as JSON ,and retrieve the response for you.
 #[derive(Parser, Debug)]
 struct Post {
@@ -65,44 +302,675 @@ struct Post {
     #[arg(value_parser = parse_url)]
     url: String,
 
-    /// HTTP post body
-    #[arg(value_parser = parse_kv_pair)]
-    body: Vec<KvPair>,
+    /// Request items: Header:value, param==value, field=value, field=@file,
+    /// field:=json, field:=@file, field@file
+    #[arg(value_parser = parse_request_item)]
+    items: Vec<RequestItem>,
+}
+
+/// send a request with any HTTP method, e.g. `request PURGE http://...`
+#[derive(Parser, Debug)]
+struct Request {
+    /// HTTP method, such as PUT, PURGE or PROPFIND
+    #[arg(value_parser = parse_method)]
+    method: Method,
+
+    #[command(flatten)]
+    args: Post,
+}
+
+/// parse a curl command, e.g. one copied from browser devtools, and send it
+#[derive(Parser, Debug)]
+struct FromCurl {
+    /// The curl command, quoted as a single argument or given as is
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+}
+
+/// post a GraphQL query to the url as `{query, variables, operationName}`,
+/// then print `data` and show `errors` separately on stderr
+#[derive(Parser, Debug)]
+struct Graphql {
+    /// GraphQL endpoint url
+    #[arg(value_parser = parse_url)]
+    url: String,
+
+    /// The query, or @path to read it from a file
+    query: String,
+
+    /// Name of the operation to run when the query defines several
+    #[arg(long)]
+    operation: Option<String>,
+
+    /// Request items: Header:value, param==value, and variables as
+    /// var=value, var:=json or var:=@file
+    #[arg(value_parser = parse_request_item)]
+    items: Vec<RequestItem>,
+}
+
+/// run the requests in a .http file (VS Code REST Client format) one by one
+#[derive(Parser, Debug)]
+struct Run {
+    /// The .http file. Requests are separated by ###, `@name = value` defines
+    /// a variable, and `# @name login` lets later requests use
+    /// {{login.response.body.$.token}} or {{login.response.headers.Name}}
+    file: PathBuf,
+
+    /// KEY=VALUE file with variables for {{KEY}}, e.g. one file per environment
+    #[arg(long)]
+    env_file: Option<PathBuf>,
 }
 
 fn parse_url(s: &str) -> Result<String> {
-    // 这里我们仅仅检查一下 URL 是否合法
-    let _url: Url = s.parse()?;
+    // 这里我们仅仅检查一下 URL 是否合法，简写要等拿到 --default-scheme 之后才展开
+    let _url: Url = expand_url(s, "http").parse()?;
 
     Ok(s.into())
 }
 
-async fn get(client: Client, args: &Get) -> Result<()> {
-    let resp = client.get(&args.url).send().await?;
-    println!("{:?}", resp.text().await?);
-    Ok(())
+fn parse_status(s: &str) -> Result<StatusCode> {
+    Ok(StatusCode::from_u16(s.trim().parse()?)?)
+}
+
+fn parse_method(s: &str) -> Result<Method> {
+    // 方法名统一转成大写，这样 `request purge ...` 也能正常工作
+    Ok(s.to_ascii_uppercase().parse()?)
+}
+
+/// Ctx::exchange 的结果：状态码、响应头以及文本形式的 body
+type Exchange = (StatusCode, HeaderMap, Option<String>);
+
+/// 一次命令行调用中所有请求共享的状态
+#[derive(Clone)]
+struct Ctx {
+    client: Client,
+    printer: Printer,
+    body_mode: BodyMode,
+    /// 从 stdin 或 --raw 得到的 body
+    raw: Option<Body>,
+    /// --http-version 指定的请求版本
+    version: Option<Version>,
+    /// 配置文件中按 host 注入的请求头
+    headers: HeaderMap,
+    auth: Option<Auth>,
+    session: Option<Session>,
+    download: Option<Download>,
+    stream: bool,
+    offline: bool,
+    to_curl: bool,
+    /// --verify 的取值，--to-curl 时转换成 curl 对应的参数
+    verify: Verify,
+    redirect: Redirect,
+    retry: Retry,
+    expect: Expect,
+}
+
+impl Ctx {
+    /// 打印并发送请求，然后输出响应并返回最终的状态码；--offline 时只打印请求
+    async fn run(&self, req: RequestBuilder) -> Result<Option<StatusCode>> {
+        let resp = self.exchange(req, false).await?;
+        Ok(resp.map(|(status, _, _)| status))
+    }
+
+    /// 发送请求、输出响应并检查 --expect-*，返回状态码、响应头以及文本形式的 body。
+    /// capture 为 false 且没有 --expect-json 时不保留 body，二进制的 body 也不会返回；
+    /// --offline 和 --to-curl 时返回 None
+    async fn exchange(&self, req: RequestBuilder, capture: bool) -> Result<Option<Exchange>> {
+        let start = Instant::now();
+        let Some((resp, meta)) = self.fetch(req).await? else {
+            return Ok(None);
+        };
+        let status = resp.status();
+        let headers = resp.headers().clone();
+        let capture = capture || !self.expect.json.is_empty();
+        let body = self
+            .output(resp, capture)
+            .await?
+            .and_then(|body| body_text(get_content_type(&headers).as_ref(), &body));
+        self.print_meta(meta, start);
+        self.expect.check(status, &headers, body.as_deref())?;
+        Ok(Some((status, headers, body)))
+    }
+
+    /// 打印并发送请求，--follow 时逐跳处理重定向，返回最终的响应；--offline 和 --to-curl 时不发送
+    async fn fetch(&self, req: RequestBuilder) -> Result<Option<(Response, Meta)>> {
+        let req = req.build()?;
+        if self.to_curl {
+            let shown = self.with_session_cookies(&req);
+            let shown = shown.as_ref().unwrap_or(&req);
+            println!(
+                "{}",
+                curl::to_curl(shown, self.auth.as_ref(), &self.verify)?
+            );
+            return Ok(None);
+        }
+        self.print_request(&req);
+        if self.offline {
+            return Ok(None);
+        }
+
+        let mut chain = Chain::new(&req);
+        let origin = req.url().clone();
+        let mut req = req;
+        let resp = loop {
+            let hop = match self.redirect.follow {
+                true => req.try_clone(),
+                false => None,
+            };
+            // 配置文件和命令行中的认证信息只属于最初请求的 host
+            let auth = self
+                .auth
+                .as_ref()
+                .filter(|_| redirect::same_host(req.url(), &origin));
+            let resp = self.execute(req, auth).await?;
+            let next = match hop {
+                Some(hop) => redirect::next_request(hop, resp.status(), resp.headers())?,
+                None => None,
+            };
+            let Some(mut next) = next else {
+                break resp;
+            };
+            // 跳到其他 host 时去掉配置文件和 session 为原 host 注入的请求头
+            if !redirect::same_host(next.url(), &origin) {
+                let mut injected = self.headers.clone();
+                if let Some(session) = &self.session {
+                    injected.extend(session.headers()?);
+                }
+                for (name, value) in &injected {
+                    if next.headers().get(name) == Some(value) {
+                        next.headers_mut().remove(name);
+                    }
+                }
+            }
+            chain.push(&next, self.redirect.max)?;
+            if self.redirect.all {
+                let has_body = resp.content_length() != Some(0);
+                self.printer.print_resp(resp).await?;
+                if has_body {
+                    println!();
+                }
+                self.print_request(&next);
+            }
+            req = next;
+        };
+        let meta = Meta::new(&resp, chain.count());
+        Ok(Some((resp, meta)))
+    }
+
+    /// 响应输出完成后打印 --meta 信息
+    fn print_meta(&self, meta: Meta, start: Instant) {
+        if self.printer.print_meta_enabled() {
+            let meta = meta.finish(start.elapsed());
+            self.printer.print_meta(&meta);
+        }
+    }
+
+    /// 发送请求，失败时按 --retries 重试
+    async fn execute(&self, mut req: reqwest::Request, auth: Option<&Auth>) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            let retry = match self.retry.allows(req.method()) {
+                true => req.try_clone(),
+                false => None,
+            };
+            let result = self.execute_once(req, auth).await;
+            let Some(retry) = retry else {
+                return result;
+            };
+            let delay = match &result {
+                Ok(resp) => self
+                    .retry
+                    .delay_for_status(resp.status(), resp.headers(), attempt),
+                Err(e) => self.retry.delay_for_error(e, attempt),
+            };
+            let Some(delay) = delay else {
+                return result;
+            };
+            let reason = match &result {
+                Ok(resp) => resp.status().to_string(),
+                Err(e) => e.to_string(),
+            };
+            attempt += 1;
+            eprintln!(
+                "{}, retrying in {:.1}s ({}/{})",
+                reason,
+                delay.as_secs_f64(),
+                attempt,
+                self.retry.retries
+            );
+            tokio::time::sleep(delay).await;
+            req = retry;
+        }
+    }
+
+    /// 发送一次请求，digest 认证时会自动完成 challenge/response
+    async fn execute_once(&self, req: reqwest::Request, auth: Option<&Auth>) -> Result<Response> {
+        match auth {
+            Some(auth) => auth.execute(&self.client, req).await,
+            None => Ok(self.client.execute(req).await?),
+        }
+    }
+
+    /// 输出响应：下载模式下 body 保存到文件，流模式下边收边打印，否则交给 printer
+    /// capture 为 true 时还会返回读取到的 body，下载和流模式下 body 不经过内存，返回 None
+    async fn output(&self, resp: Response, capture: bool) -> Result<Option<Vec<u8>>> {
+        match &self.download {
+            Some(download) => {
+                self.printer.print_head(&resp);
+                download.save(resp).await?;
+            }
+            None if self.stream => self.printer.print_stream(resp).await?,
+            None if capture => {
+                self.printer.print_head(&resp);
+                let mime = get_content_type(resp.headers());
+                let body = resp.bytes().await?.to_vec();
+                self.printer.print_resp_bytes(mime, &body)?;
+                return Ok(Some(body));
+            }
+            None => self.printer.print_resp(resp).await?,
+        }
+        Ok(None)
+    }
+
+    /// 输出请求时带上发送时才会加上的 session cookie
+    fn print_request(&self, req: &reqwest::Request) {
+        let shown = self.with_session_cookies(req);
+        self.printer.print_request(shown.as_ref().unwrap_or(req));
+    }
+
+    /// session 的 cookie 由 reqwest 在发送时才加上，输出请求时用这个函数补上 Cookie 头。
+    /// 请求本身不能带上 Cookie 头，否则 reqwest 不会再为重定向之后的请求更新 cookie。
+    /// 没有需要补上的 cookie 时返回 None
+    fn with_session_cookies(&self, req: &reqwest::Request) -> Option<reqwest::Request> {
+        if req.headers().contains_key(header::COOKIE) {
+            return None;
+        }
+        let cookie = self.session.as_ref()?.cookie_store().cookies(req.url())?;
+        let mut req = req.try_clone()?;
+        req.headers_mut().insert(header::COOKIE, cookie);
+        Some(req)
+    }
+
+    /// run 中每个请求的 host 都可能不同，换成该 host 的配置请求头和认证信息
+    fn with_host(&self, headers: HeaderMap, auth: Option<Auth>) -> Ctx {
+        Ctx {
+            headers,
+            auth,
+            session: None,
+            ..self.clone()
+        }
+    }
+}
+
+async fn get(ctx: &Ctx, args: &Get) -> Result<Option<StatusCode>> {
+    let req = build_request(ctx, Method::GET, &args.url, &args.items, ctx.raw.as_ref())?;
+    ctx.run(req).await
+}
+
+async fn post(ctx: &Ctx, args: &Post) -> Result<Option<StatusCode>> {
+    send(ctx, Method::POST, args).await
+}
+
+/// 除 get 以外的方法都走这里
+async fn send(ctx: &Ctx, method: Method, args: &Post) -> Result<Option<StatusCode>> {
+    let req = build_request(ctx, method, &args.url, &args.items, ctx.raw.as_ref())?;
+    ctx.run(req).await
+}
+
+/// 把 query 和变量包装成 GraphQL 请求后交给 post，响应由 printer 按 GraphQL 的格式输出
+async fn graphql(ctx: &Ctx, args: &Graphql) -> Result<Option<StatusCode>> {
+    if ctx.body_mode != BodyMode::Json {
+        return Err(anyhow!(
+            "GraphQL requests are always sent as JSON, --form and --multipart cannot be used"
+        ));
+    }
+    let post_args = Post {
+        url: args.url.clone(),
+        items: graphql::envelope(&args.query, args.operation.as_deref(), &args.items)?,
+    };
+    post(ctx, &post_args).await
+}
+
+/// 建立 WebSocket 连接，请求头、认证等和 get 一样由 request items 和全局选项决定
+async fn ws(ctx: &Ctx, args: &Get, conn: &ConnectionArgs) -> Result<Option<StatusCode>> {
+    if ctx.to_curl {
+        return Err(anyhow!("--to-curl cannot be used with ws"));
+    }
+    // 握手不经过 reqwest，代理和需要额外往返的 digest 认证都不会生效
+    if !conn.proxy.is_empty() {
+        return Err(anyhow!("--proxy cannot be used with ws"));
+    }
+    if ctx
+        .auth
+        .as_ref()
+        .is_some_and(|a| a.auth_type == AuthType::Digest)
+    {
+        return Err(anyhow!("Digest auth cannot be used with ws"));
+    }
+    let url = ws::http_url(&args.url)?;
+    let mut req = build_request(ctx, Method::GET, &url, &args.items, None)?.build()?;
+    // 握手不经过 reqwest，session 的 cookie 需要自己加上，响应中的 Set-Cookie 也要自己记录
+    if let Some(with_cookies) = ctx.with_session_cookies(&req) {
+        req = with_cookies;
+    }
+    let cookies = ctx.session.as_ref().map(Session::cookie_store);
+    let handshake = ws::handshake(&mut req)?;
+    ctx.printer.print_request(&req);
+    if ctx.offline {
+        return Ok(None);
+    }
+
+    let connector = Connector::NativeTls(conn.tls_connector()?);
+    let (socket, resp) =
+        tokio_tungstenite::connect_async_tls_with_config(handshake, None, false, Some(connector))
+            .await?;
+    ctx.printer
+        .print_response_head(resp.version(), resp.status(), resp.headers());
+    if let Some(cookies) = &cookies {
+        cookies.set_cookies(
+            &mut resp.headers().get_all(header::SET_COOKIE).iter(),
+            req.url(),
+        );
+    }
+    ws::chat(&ctx.printer, socket, ws::stdin_lines()).await?;
+    Ok(Some(resp.status()))
+}
+
+/// 依次执行 .http 文件中的请求，命名请求的响应会被记录下来供之后的请求引用。
+/// 返回第一个失败的状态码，都成功时返回最后一个状态码
+async fn run_file(
+    ctx: &Ctx,
+    args: &Run,
+    default_scheme: &str,
+    config: &Config,
+    auth: Option<&Auth>,
+    auth_type: AuthType,
+) -> Result<Option<StatusCode>> {
+    let content = fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read {}", args.file.display()))?;
+    let dir = args.file.parent().unwrap_or(Path::new("."));
+    let mut vars = Variables::default();
+    if let Some(env_file) = &args.env_file {
+        vars.load_env(env_file)?;
+    }
+
+    let mut result: Option<StatusCode> = None;
+    for block in http_file::parse(&content, dir)? {
+        vars.define(&block.variables);
+        let Some(req) = &block.request else {
+            continue;
+        };
+        let req = vars.resolve_request(req)?;
+        if result.is_some() {
+            println!();
+        }
+        let title = block.title.or(block.name.clone());
+        ctx.printer
+            .print_title(&title.unwrap_or_else(|| format!("{} {}", req.method, req.url)));
+
+        // Content-Type 跟着 body 走，避免请求中出现两个 Content-Type
+        let mut content_type = None;
+        let mut items = Vec::new();
+        for (k, v) in req.headers {
+            match k.eq_ignore_ascii_case(header::CONTENT_TYPE.as_str()) {
+                true => content_type = Some(v),
+                false => items.push(RequestItem::Header(k, v)),
+            }
+        }
+        let body = req
+            .body
+            .map(|body| items::raw_body(body.into_bytes(), content_type.as_deref()));
+        let url = expand_url(&req.url, default_scheme);
+        // 命令行中的 --auth 优先，其次是配置文件中这个请求所在 host 的认证信息
+        let host = config.host(&url).cloned().unwrap_or_default();
+        let auth = match auth {
+            Some(auth) => Some(auth.clone()),
+            None => host.auth(auth_type)?,
+        };
+        let ctx = &ctx.with_host(host.headers()?, auth);
+        let builder = build_request(ctx, parse_method(&req.method)?, &url, &items, body.as_ref())?;
+
+        // --expect-* 对文件中的每一个响应都生效，第一个不满足的请求就会中止执行
+        let Some((status, headers, body)) = ctx
+            .exchange(builder, block.name.is_some())
+            .await
+            .with_context(|| format!("Request {} {}", req.method, req.url))?
+        else {
+            continue;
+        };
+        // 二进制的响应没有可以引用的 body
+        if let Some(name) = &block.name {
+            vars.capture(name, headers, body.unwrap_or_default());
+        }
+
+        let failed = |s| ExitStatus::from_status(s, ctx.redirect.follow) != ExitStatus::Success;
+        if !result.is_some_and(failed) {
+            result = Some(status);
+        }
+    }
+    Ok(result)
+}
+
+/// 根据 session 和 request items 组装请求：请求头、URL query、认证信息以及按 body mode 序列化的 body
+fn build_request(
+    ctx: &Ctx,
+    method: Method,
+    url: &str,
+    items: &[RequestItem],
+    raw: Option<&Body>,
+) -> Result<RequestBuilder> {
+    let mut req = ctx.client.request(method, url).headers(ctx.headers.clone());
+    if let Some(version) = ctx.version {
+        req = req.version(version);
+    }
+    if let Some(session) = &ctx.session {
+        req = req.headers(session.headers()?);
+    }
+    req = req
+        .headers(items::headers(items)?)
+        .query(&items::query(items));
+    let body = match (items::body(items, ctx.body_mode)?, raw) {
+        (Some(_), Some(_)) => {
+            return Err(anyhow!(
+                "Request body from stdin or --raw cannot be mixed with data items, use --ignore-stdin to skip stdin"
+            ))
+        }
+        (body, raw) => body.or_else(|| raw.cloned()),
+    };
+    if let Some(body) = body {
+        req = req
+            .header(header::CONTENT_TYPE, body.content_type)
+            .body(body.data);
+    }
+    if let Some(range) = ctx.download.as_ref().and_then(Download::range) {
+        req = req.header(header::RANGE, range);
+    }
+    if let Some(auth) = &ctx.auth {
+        req = auth.apply(req);
+    }
+    Ok(req)
 }
 
-async fn post(client: Client, args: &Post) -> Result<()> {
-    let mut body = HashMap::new();
-    for pair in args.body.iter() {
-        body.insert(&pair.k, &pair.v);
+/// --raw 或者 stdin 中的原始 body。stdin 不是 TTY 时才读取，内容为空时当作没有 body
+fn raw_body(opts: &Opts) -> Result<Option<Body>> {
+    let content_type = opts.content_type.as_deref();
+    if let Some(raw) = &opts.raw {
+        return Ok(Some(items::raw_body(
+            raw.clone().into_bytes(),
+            content_type,
+        )));
+    }
+    let mut stdin = std::io::stdin();
+    if opts.ignore_stdin || stdin.is_terminal() {
+        return Ok(None);
     }
-    let resp = client.post(&args.url).json(&body).send().await?;
-    println!("{:?}", resp.text().await?);
+    let mut data = Vec::new();
+    stdin.read_to_end(&mut data)?;
+    Ok((!data.is_empty()).then(|| items::raw_body(data, content_type)))
+}
 
-    Ok(())
+/// 解析命令行参数。from-curl 之前的选项保留，之后的部分替换成 curl 命令对应的参数再解析一次
+fn parse_opts(config: &Config) -> Result<Opts> {
+    let args = config.args(std::env::args_os());
+    let opts = Opts::parse_from(&args);
+    let SubCommand::FromCurl(ref cmd) = opts.subcmd else {
+        return Ok(opts);
+    };
+    let pos = args
+        .iter()
+        .position(|arg| arg == "from-curl")
+        .unwrap_or(args.len());
+    let mut args = args[..pos].to_vec();
+    args.extend(
+        curl::from_curl(&cmd.command)?
+            .into_iter()
+            .map(OsString::from),
+    );
+    Ok(Opts::parse_from(args))
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let opts: Opts = Opts::parse();
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(status) => status.into(),
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitStatus::from_error(&e).into()
+        }
+    }
+}
 
-    let client = Client::new();
-    let result = match opts.subcmd {
-        SubCommand::Get(ref args) => get(client, args).await?,
-        SubCommand::Post(ref args) => post(client, args).await?,
+async fn run() -> Result<ExitStatus> {
+    let config = Config::load()?;
+    let mut opts = parse_opts(&config)?;
+    if let Some(url) = opts.subcmd.url_mut() {
+        *url = expand_url(url, &opts.default_scheme);
+    }
+
+    // session 按 host 保存，而 .http 文件中的请求可能发往不同的 host
+    if matches!(opts.subcmd, SubCommand::Run(_))
+        && (opts.session.is_some() || opts.session_read_only.is_some())
+    {
+        return Err(anyhow!(
+            "--session cannot be used with run, requests in a file may go to different hosts"
+        ));
+    }
+    let (url, items) = opts.subcmd.target().unwrap_or_default();
+    let session = match (&opts.session, &opts.session_read_only) {
+        (Some(name), _) => Some(Session::load(name, url, false)?),
+        (_, Some(name)) => Some(Session::load(name, url, true)?),
+        _ => None,
     };
+    let auth = match opts.auth {
+        Some(ref credentials) => Some(Auth::new(opts.auth_type, credentials)?),
+        None => None,
+    };
+    let host = config.host(url).cloned().unwrap_or_default();
 
-    Ok(result)
+    // --verbose 输出请求和响应，--offline 时默认输出整个请求
+    let mut print = match (opts.print, opts.verbose, opts.offline) {
+        (None, _, true) => Some("HB".parse()?),
+        (None, true, _) => Some("HBhb".parse()?),
+        (print, _, _) => print,
+    };
+    if opts.meta {
+        let mut p = print.unwrap_or_else(Print::detect);
+        p.meta = true;
+        print = Some(p);
+    }
+
+    // 重定向由 Ctx::run 逐跳处理，这样才能输出中间的响应
+    let mut client = opts
+        .conn
+        .apply(Client::builder().redirect(reqwest::redirect::Policy::none()))?;
+    if let Some(session) = &session {
+        client = client.cookie_provider(session.cookie_store());
+    }
+    if let Some(timeout) = opts.timeout {
+        client = client.timeout(Duration::try_from_secs_f64(timeout)?);
+    }
+    let mut printer = Printer::new(print, opts.pretty, opts.filter.clone());
+    if let SubCommand::Graphql(_) = opts.subcmd {
+        printer = printer.graphql();
+    }
+    let ctx = Ctx {
+        client: client.build()?,
+        printer,
+        body_mode: opts.body_mode(),
+        // ws 子命令中 stdin 是要发送的消息，不能当作请求 body 读掉
+        raw: match opts.subcmd {
+            SubCommand::Ws(_) => None,
+            _ => raw_body(&opts)?,
+        },
+        version: opts.conn.version(),
+        headers: host.headers()?,
+        // 命令行中的 --auth 优先，其次是配置文件，最后是 session 中保存的认证信息
+        auth: match auth.clone() {
+            Some(auth) => Some(auth),
+            None => host.auth(opts.auth_type)?,
+        }
+        .or_else(|| session.as_ref().and_then(|s| s.auth().cloned())),
+        session,
+        download: match opts.download {
+            true => Some(Download::new(opts.output.clone(), opts.resume)?),
+            false => None,
+        },
+        stream: opts.stream,
+        offline: opts.offline,
+        to_curl: opts.to_curl,
+        verify: opts.conn.verify.clone(),
+        redirect: Redirect {
+            follow: opts.follow,
+            max: opts.max_redirects,
+            all: opts.all,
+        },
+        retry: Retry {
+            retries: opts.retries,
+            on: opts.retry_on.clone(),
+            force: opts.retry_all_methods,
+        },
+        expect: Expect {
+            status: opts.expect_status.clone(),
+            headers: opts.expect_header.clone(),
+            json: opts.expect_json.clone(),
+        },
+    };
+    let status = match opts.subcmd {
+        SubCommand::Get(ref args) => get(&ctx, args).await?,
+        SubCommand::Post(ref args) => post(&ctx, args).await?,
+        SubCommand::Put(ref args) => send(&ctx, Method::PUT, args).await?,
+        SubCommand::Patch(ref args) => send(&ctx, Method::PATCH, args).await?,
+        SubCommand::Delete(ref args) => send(&ctx, Method::DELETE, args).await?,
+        SubCommand::Head(ref args) => send(&ctx, Method::HEAD, args).await?,
+        SubCommand::Options(ref args) => send(&ctx, Method::OPTIONS, args).await?,
+        SubCommand::Request(ref args) => send(&ctx, args.method.clone(), &args.args).await?,
+        SubCommand::FromCurl(_) => unreachable!("from-curl is translated by parse_opts"),
+        SubCommand::Run(ref args) => {
+            run_file(
+                &ctx,
+                args,
+                &opts.default_scheme,
+                &config,
+                auth.as_ref(),
+                opts.auth_type,
+            )
+            .await?
+        }
+        SubCommand::Ws(ref args) => ws(&ctx, args, &opts.conn).await?,
+        SubCommand::Graphql(ref args) => graphql(&ctx, args).await?,
+    };
+
+    // 请求成功后把本次的请求头、认证信息以及服务器设置的 cookie 写回 session
+    if let Some(session) = ctx.session.filter(|_| !opts.offline && !opts.to_curl) {
+        session.save(&items::headers(items)?, auth.as_ref())?;
+    }
+
+    let Some(status) = status.filter(|_| opts.check_status) else {
+        return Ok(ExitStatus::Success);
+    };
+    let exit = ExitStatus::from_status(status, opts.follow);
+    if exit != ExitStatus::Success {
+        eprintln!("Warning: HTTP {}", status);
+    }
+    Ok(exit)
 }
@@ -0,0 +1,27 @@
+use reqwest::Response;
+use std::{net::SocketAddr, time::Duration};
+
+/// --meta 输出的请求元信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct Meta {
+    pub elapsed: Duration,
+    pub remote_addr: Option<SocketAddr>,
+    pub redirects: usize,
+}
+
+impl Meta {
+    /// 在 body 被读取之前从响应中收集元信息
+    pub fn new(resp: &Response, redirects: usize) -> Self {
+        Self {
+            elapsed: Duration::default(),
+            remote_addr: resp.remote_addr(),
+            redirects,
+        }
+    }
+
+    /// 响应输出完成后补上耗时
+    pub fn finish(mut self, elapsed: Duration) -> Self {
+        self.elapsed = elapsed;
+        self
+    }
+}
@@ -0,0 +1,570 @@
+use crate::{
+    filter::Filter,
+    graphql,
+    highlight::{colorize_markup, colorize_yaml, indent_markup},
+    meta::Meta,
+    sse::{Event, SseParser},
+};
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use colored::Colorize;
+use mime::Mime;
+use reqwest::{header, header::HeaderMap, Request, Response, StatusCode, Version};
+use std::{
+    io::{IsTerminal, Write},
+    str::FromStr,
+};
+
+/// --pretty 控制输出样式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Pretty {
+    /// 格式化并着色
+    All,
+    /// 只着色
+    Colors,
+    /// 只格式化
+    Format,
+    /// 原样输出
+    None,
+}
+
+/// --print 控制输出哪些部分：H / B 表示请求头（含请求行）和请求 body，
+/// h / b 表示响应头（含状态行）和响应 body，m 表示耗时、远端地址等元信息
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Print {
+    pub request_headers: bool,
+    pub request_body: bool,
+    pub headers: bool,
+    pub body: bool,
+    pub meta: bool,
+}
+
+impl FromStr for Print {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut print = Print::default();
+        for c in s.chars() {
+            match c {
+                'H' => print.request_headers = true,
+                'B' => print.request_body = true,
+                'h' => print.headers = true,
+                'b' => print.body = true,
+                'm' => print.meta = true,
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid --print option {}, expect H, B, h, b or m",
+                        c
+                    ))
+                }
+            }
+        }
+        Ok(print)
+    }
+}
+
+impl Print {
+    /// 没有指定 --print 时的默认值：和 httpie 一样，输出被重定向时只输出 body
+    pub fn detect() -> Self {
+        Print {
+            headers: std::io::stdout().is_terminal(),
+            body: true,
+            ..Default::default()
+        }
+    }
+}
+
+pub fn parse_print(s: &str) -> Result<Print> {
+    s.parse()
+}
+
+/// 和 httpie 一样，除了 Content-Type 是二进制类型，包含 NUL 字符的内容也当作二进制
+pub fn is_binary(mime: Option<&Mime>, body: &[u8]) -> bool {
+    Syntax::detect(mime) == Syntax::Binary || body.contains(&0)
+}
+
+/// 把读取到的 body 解码成文本，二进制内容返回 None
+pub fn body_text(mime: Option<&Mime>, body: &[u8]) -> Option<String> {
+    (!is_binary(mime, body)).then(|| String::from_utf8_lossy(body).into_owned())
+}
+
+/// 二进制的 body 不输出到终端，只显示这段提示
+const BINARY_NOTICE: &str = "\
++-----------------------------------------+
+| NOTE: binary data not shown in terminal |
++-----------------------------------------+";
+
+/// 根据 Content-Type 决定 body 的渲染方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Syntax {
+    Json,
+    Xml,
+    Html,
+    Yaml,
+    Binary,
+    Text,
+}
+
+impl Syntax {
+    fn detect(m: Option<&Mime>) -> Self {
+        let Some(m) = m else {
+            return Syntax::Text;
+        };
+        let suffix = m.suffix().map(|s| s.as_str());
+        match (m.type_().as_str(), m.subtype().as_str(), suffix) {
+            _ if is_json(m) => Syntax::Json,
+            (_, "html", _) | (_, "xhtml", Some("xml")) => Syntax::Html,
+            (_, "xml", _) | (_, _, Some("xml")) => Syntax::Xml,
+            (_, "yaml" | "x-yaml", _) | (_, _, Some("yaml")) => Syntax::Yaml,
+            ("image" | "audio" | "video" | "font", ..) => Syntax::Binary,
+            ("application", "octet-stream" | "pdf" | "zip" | "gzip" | "wasm", _) => Syntax::Binary,
+            _ => Syntax::Text,
+        }
+    }
+}
+
+/// 响应输出的配置，由命令行参数和 stdout 是否为 TTY 共同决定
+#[derive(Debug, Clone)]
+pub struct Printer {
+    print: Print,
+    colors: bool,
+    format: bool,
+    filter: Option<Filter>,
+    /// graphql 子命令：JSON 响应中的 data 和 errors 分开输出
+    graphql: bool,
+}
+
+impl Printer {
+    pub fn new(print: Option<Print>, pretty: Option<Pretty>, filter: Option<Filter>) -> Self {
+        let tty = std::io::stdout().is_terminal();
+        // 和 httpie 一样，输出被重定向时默认不做任何美化
+        let print = print.unwrap_or_else(Print::detect);
+        let pretty = pretty.unwrap_or(if tty { Pretty::All } else { Pretty::None });
+        let colors = matches!(pretty, Pretty::All | Pretty::Colors);
+        colored::control::set_override(colors);
+
+        Self {
+            print,
+            colors,
+            format: matches!(pretty, Pretty::All | Pretty::Format),
+            filter,
+            graphql: false,
+        }
+    }
+
+    /// 按 GraphQL 响应的格式输出 JSON body
+    pub fn graphql(self) -> Self {
+        Self {
+            graphql: true,
+            ..self
+        }
+    }
+
+    /// 按 HTTP/1.1 报文格式打印即将发送的请求：请求行、请求头以及 body
+    pub fn print_request(&self, req: &Request) {
+        if self.print.request_headers {
+            let url = req.url();
+            let path = match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            };
+            let line = format!("{} {} {:?}", req.method(), path, req.version());
+            println!("{}", line.blue());
+
+            let mut headers = HeaderMap::new();
+            if let Some(host) = url.host_str() {
+                let host = match url.port() {
+                    Some(port) => format!("{}:{}", host, port),
+                    None => host.to_string(),
+                };
+                headers.insert(header::HOST, host.parse().unwrap());
+            }
+            headers.extend(req.headers().clone());
+            if let Some(len) = req.body().and_then(|b| b.as_bytes()).map(<[u8]>::len) {
+                headers.insert(header::CONTENT_LENGTH, len.into());
+            }
+            print_headers(&headers);
+        }
+        let body = req.body().and_then(|b| b.as_bytes()).unwrap_or_default();
+        if self.print.request_body && !body.is_empty() {
+            match std::str::from_utf8(body) {
+                Ok(body) => self.print_body(get_content_type(req.headers()), body),
+                Err(_) => println!("<binary data, {} bytes>", body.len()),
+            }
+            if self.print.headers || self.print.body || self.print.meta {
+                println!();
+            }
+        }
+    }
+
+    pub fn print_meta_enabled(&self) -> bool {
+        self.print.meta
+    }
+
+    /// 打印 --meta 信息
+    pub fn print_meta(&self, meta: &Meta) {
+        let rows = [
+            (
+                "Elapsed time",
+                format!("{:.3}s", meta.elapsed.as_secs_f64()),
+            ),
+            (
+                "Remote address",
+                meta.remote_addr
+                    .map(|addr| addr.to_string())
+                    .unwrap_or_else(|| "unknown".into()),
+            ),
+            ("Redirects", meta.redirects.to_string()),
+        ];
+        if self.print.headers || self.print.body {
+            println!();
+        }
+        for (name, value) in rows {
+            println!("{}: {}", name.cyan(), value);
+        }
+    }
+
+    /// 打印状态行、响应头以及根据 Content-Type 美化过的 body
+    pub async fn print_resp(&self, resp: Response) -> Result<()> {
+        self.print_head(&resp);
+        if !self.print.body {
+            return Ok(());
+        }
+        let mime = get_content_type(resp.headers());
+        // 二进制内容不能按文本解码，重定向到文件时要原样输出
+        if Syntax::detect(mime.as_ref()) == Syntax::Binary {
+            let body = resp.bytes().await?;
+            return self.print_binary(&body);
+        }
+        let body = resp.text().await?;
+        self.print_resp_body(mime, body)
+    }
+
+    /// 打印以字节形式读取出来的响应 body，二进制内容不做解码
+    pub fn print_resp_bytes(&self, mime: Option<Mime>, body: &[u8]) -> Result<()> {
+        if !self.print.body {
+            return Ok(());
+        }
+        match body_text(mime.as_ref(), body) {
+            Some(text) => self.print_resp_body(mime, text),
+            None => self.print_binary(body),
+        }
+    }
+
+    /// 执行 .http 文件时，在每个请求之前输出它的标题
+    pub fn print_title(&self, title: &str) {
+        println!("{} {}", "###".purple(), title.bold());
+    }
+
+    /// 打印已经读取出来的响应 body，JSON 会先经过 --filter
+    pub fn print_resp_body(&self, mime: Option<Mime>, body: String) -> Result<()> {
+        if !self.print.body {
+            return Ok(());
+        }
+        if is_binary(mime.as_ref(), body.as_bytes()) {
+            return self.print_binary(body.as_bytes());
+        }
+        if self.graphql && mime.as_ref().is_some_and(is_json) {
+            if let Some(resp) = graphql::parse_response(&body) {
+                self.print_graphql(resp);
+                return Ok(());
+            }
+        }
+        let body = match (&self.filter, &mime) {
+            (Some(filter), Some(m)) if is_json(m) => filter_json(filter, body),
+            _ => body,
+        };
+        self.print_body(mime, &body);
+        Ok(())
+    }
+
+    /// data 输出到 stdout，--filter 作用在 data 上；errors 用红色输出到 stderr，
+    /// 这样把输出交给 jq 之类的工具时不会混在一起
+    fn print_graphql(&self, resp: graphql::Response) {
+        if let Some(data) = resp.data {
+            let data = match &self.filter {
+                Some(filter) => filter.apply(&data),
+                None => data,
+            };
+            println!("{}", self.format_json(&data.to_string()));
+        }
+        for error in resp.errors {
+            eprintln!("{} {}", "GraphQL error:".red().bold(), error.red());
+        }
+    }
+
+    /// 输出到终端时只显示提示，重定向时原样输出
+    fn print_binary(&self, body: &[u8]) -> Result<()> {
+        if body.is_empty() {
+            return Ok(());
+        }
+        let mut stdout = std::io::stdout();
+        match stdout.is_terminal() {
+            true => println!("{}", BINARY_NOTICE),
+            false => stdout.write_all(body)?,
+        }
+        Ok(())
+    }
+
+    /// 只打印状态行和响应头，body 由调用者自行处理（比如下载到文件）
+    pub fn print_head(&self, resp: &Response) {
+        self.print_response_head(resp.version(), resp.status(), resp.headers());
+    }
+
+    /// 打印不是由 reqwest 发出的请求的响应头，比如 WebSocket 的握手响应
+    pub fn print_response_head(&self, version: Version, status: StatusCode, headers: &HeaderMap) {
+        if self.print.headers {
+            print_status(version, status);
+            print_headers(headers);
+        }
+    }
+
+    /// 输出一条 WebSocket 文本消息，JSON 和响应 body 一样经过 --filter 并美化
+    pub fn print_message(&self, text: &str) {
+        if !self.print.body {
+            return;
+        }
+        match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(value) => match &self.filter {
+                Some(filter) => println!("{}", self.format_json(&filter.apply(&value).to_string())),
+                None => println!("{}", self.format_json(text)),
+            },
+            Err(_) => println!("{}", text),
+        }
+    }
+
+    /// 二进制消息只输出长度
+    pub fn print_binary_message(&self, len: usize) {
+        if self.print.body {
+            println!("<binary message, {} bytes>", len);
+        }
+    }
+
+    /// --stream 模式：body 到达一块就输出一块，text/event-stream 会按事件解析后输出
+    pub async fn print_stream(&self, mut resp: Response) -> Result<()> {
+        self.print_head(&resp);
+        if !self.print.body {
+            return Ok(());
+        }
+        let sse = get_content_type(resp.headers())
+            .is_some_and(|m| m.essence_str() == mime::TEXT_EVENT_STREAM.essence_str());
+        let mut parser = SseParser::default();
+        let mut stdout = std::io::stdout();
+        while let Some(chunk) = resp.chunk().await? {
+            match sse {
+                true => parser
+                    .feed(&chunk)
+                    .iter()
+                    .for_each(|event| self.print_event(event)),
+                false => stdout.write_all(&chunk)?,
+            }
+            stdout.flush()?;
+        }
+        if let Some(event) = parser.finish() {
+            self.print_event(&event);
+        }
+        Ok(())
+    }
+
+    fn print_body(&self, m: Option<Mime>, body: &str) {
+        if body.is_empty() {
+            return;
+        }
+        match Syntax::detect(m.as_ref()) {
+            Syntax::Json => println!("{}", self.format_json(body)),
+            Syntax::Xml => println!("{}", self.format_markup(body, false)),
+            Syntax::Html => println!("{}", self.format_markup(body, true)),
+            Syntax::Yaml if self.colors => println!("{}", colorize_yaml(body)),
+            _ => println!("{}", body),
+        }
+    }
+
+    /// 按照 --pretty 的配置格式化并着色 JSON
+    fn format_json(&self, body: &str) -> String {
+        let body = match self.format {
+            true => jsonxf::pretty_print(body).unwrap_or_else(|_| body.to_string()),
+            false => body.to_string(),
+        };
+        match self.colors {
+            true => colorize_json(&body),
+            false => body,
+        }
+    }
+
+    /// 按照 --pretty 的配置缩进并着色 XML/HTML
+    fn format_markup(&self, body: &str, html: bool) -> String {
+        let body = match self.format {
+            true => indent_markup(body, html),
+            false => body.to_string(),
+        };
+        match self.colors {
+            true => colorize_markup(&body, html),
+            false => body,
+        }
+    }
+
+    /// 输出一个 SSE 事件，data 是 JSON 时做美化
+    fn print_event(&self, event: &Event) {
+        if let Some(name) = &event.event {
+            println!("{} {}", "event:".purple(), name);
+        }
+        if let Some(id) = &event.id {
+            println!("{} {}", "id:".purple(), id);
+        }
+        if let Some(retry) = event.retry {
+            println!("{} {}", "retry:".purple(), retry);
+        }
+        let data = match serde_json::from_str::<serde_json::Value>(&event.data) {
+            Ok(_) => self.format_json(&event.data),
+            Err(_) => event.data.clone(),
+        };
+        println!("{} {}\n", "data:".purple(), data);
+    }
+}
+
+/// 对 JSON body 应用 --filter，body 不是合法的 JSON 时原样返回
+fn filter_json(filter: &Filter, body: String) -> String {
+    match serde_json::from_str(&body) {
+        Ok(value) => filter.apply(&value).to_string(),
+        Err(_) => body,
+    }
+}
+
+/// 打印服务器返回的 HTTP 版本和状态码
+fn print_status(version: Version, status: StatusCode) {
+    let status = format!("{:?} {}", version, status).blue();
+    println!("{}", status);
+}
+
+/// 打印服务器返回的 HTTP header
+fn print_headers(headers: &HeaderMap) {
+    for (name, value) in headers {
+        println!(
+            "{}: {}",
+            name.to_string().cyan(),
+            String::from_utf8_lossy(value.as_bytes())
+        );
+    }
+    println!();
+}
+
+/// 将服务器返回的 content-type 解析成 Mime 类型
+pub fn get_content_type(headers: &HeaderMap) -> Option<Mime> {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+/// application/json 以及 application/problem+json 这类带 +json 后缀的都当作 JSON
+fn is_json(m: &Mime) -> bool {
+    m.subtype() == mime::JSON || m.suffix() == Some(mime::JSON)
+}
+
+/// 给 JSON 文本着色：key 为蓝色，字符串为绿色，数字、布尔和 null 为黄色
+fn colorize_json(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let mut token = String::from('"');
+                while let Some(c) = chars.next() {
+                    token.push(c);
+                    match c {
+                        '\\' => token.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+                // 字符串后面紧跟（忽略空白）冒号的是 key
+                let rest = chars.clone().find(|c| !c.is_whitespace());
+                match rest {
+                    Some(':') => out.push_str(&token.blue().to_string()),
+                    _ => out.push_str(&token.green().to_string()),
+                }
+            }
+            c if c == '-' || c.is_ascii_alphanumeric() => {
+                let mut token = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-')) {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+                out.push_str(&token.yellow().to_string());
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_print_should_work() {
+        assert_eq!(
+            parse_print("hb").unwrap(),
+            Print {
+                headers: true,
+                body: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            parse_print("Bb").unwrap(),
+            Print {
+                request_body: true,
+                body: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            parse_print("bm").unwrap(),
+            Print {
+                body: true,
+                meta: true,
+                ..Default::default()
+            }
+        );
+        assert!(parse_print("x").is_err());
+    }
+
+    #[test]
+    fn is_json_should_work() {
+        assert!(is_json(&"application/json; charset=utf-8".parse().unwrap()));
+        assert!(is_json(&"application/problem+json".parse().unwrap()));
+        assert!(!is_json(&"text/html".parse().unwrap()));
+    }
+
+    #[test]
+    fn syntax_should_follow_content_type() {
+        let detect = |s: &str| Syntax::detect(Some(&s.parse().unwrap()));
+        assert_eq!(detect("application/vnd.api+json"), Syntax::Json);
+        assert_eq!(detect("text/html; charset=utf-8"), Syntax::Html);
+        assert_eq!(detect("application/xhtml+xml"), Syntax::Html);
+        assert_eq!(detect("text/xml"), Syntax::Xml);
+        assert_eq!(detect("image/svg+xml"), Syntax::Xml);
+        assert_eq!(detect("application/x-yaml"), Syntax::Yaml);
+        assert_eq!(detect("image/png"), Syntax::Binary);
+        assert_eq!(detect("application/octet-stream"), Syntax::Binary);
+        assert_eq!(detect("text/plain"), Syntax::Text);
+        assert_eq!(Syntax::detect(None), Syntax::Text);
+
+        let png: Mime = "image/png".parse().unwrap();
+        assert_eq!(body_text(Some(&png), b"abc"), None);
+        assert_eq!(body_text(None, b"a\0b"), None);
+        assert_eq!(body_text(None, b"{}").as_deref(), Some("{}"));
+    }
+
+    #[test]
+    fn colorize_json_should_keep_content() {
+        colored::control::set_override(false);
+        let s = "{\"a\": [1, -2.5e3, true, null, \"x\\\"y\"]}";
+        assert_eq!(colorize_json(s), s);
+    }
+}
@@ -0,0 +1,208 @@
+use anyhow::{anyhow, Result};
+use reqwest::{
+    header::{self, HeaderMap},
+    Method, Request, StatusCode, Url,
+};
+
+/// 重定向相关的配置，reqwest 的自动重定向被关闭，由我们自己逐跳处理
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Redirect {
+    pub follow: bool,
+    pub max: usize,
+    pub all: bool,
+}
+
+/// 重定向中的一跳。只有方法、URL 和 body 都相同的请求重复出现时才算循环，
+/// 例如 POST 之后 303 到同一个 URL 的 GET 不是循环
+type Hop = (Method, Url, Option<Vec<u8>>);
+
+/// 记录一次请求经过的所有跳转，用于检测重定向循环和输出错误信息
+#[derive(Debug, Clone, Default)]
+pub struct Chain(Vec<Hop>);
+
+impl Chain {
+    pub fn new(req: &Request) -> Self {
+        Self(vec![hop(req)])
+    }
+
+    /// 加入下一跳，出现循环或超过最大跳转次数时报错
+    pub fn push(&mut self, req: &Request, max: usize) -> Result<()> {
+        let hop = hop(req);
+        let looped = self.0.contains(&hop);
+        self.0.push(hop);
+        match (looped, self.count() > max) {
+            (true, _) => Err(anyhow!("Redirect loop detected:\n{}", self)),
+            (_, true) => Err(anyhow!("Too many redirects (max {}):\n{}", max, self)),
+            _ => Ok(()),
+        }
+    }
+
+    /// 重定向次数，不包含最初的请求
+    pub fn count(&self) -> usize {
+        self.0.len() - 1
+    }
+}
+
+fn hop(req: &Request) -> Hop {
+    (
+        req.method().clone(),
+        req.url().clone(),
+        req.body().and_then(|b| b.as_bytes()).map(Vec::from),
+    )
+}
+
+impl std::fmt::Display for Chain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (method, url, _)) in self.0.iter().enumerate() {
+            writeln!(f, "  {}. {} {}", i + 1, method, url)?;
+        }
+        Ok(())
+    }
+}
+
+/// 根据 3xx 响应计算下一跳请求，不需要跳转时返回 None
+///
+/// 301 / 302 下的 POST 和 303 下除 HEAD 以外的方法都会改成不带 body 的 GET，
+/// 307 / 308 保持原来的方法和 body
+pub fn next_request(
+    mut req: Request,
+    status: StatusCode,
+    headers: &HeaderMap,
+) -> Result<Option<Request>> {
+    if !status.is_redirection() {
+        return Ok(None);
+    }
+    let Some(location) = headers.get(header::LOCATION) else {
+        return Ok(None);
+    };
+    let location = location
+        .to_str()
+        .map_err(|_| anyhow!("Invalid Location header in {} response", status))?;
+    let url = req.url().join(location)?;
+
+    let to_get = match status {
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => req.method() == Method::POST,
+        StatusCode::SEE_OTHER => req.method() != Method::HEAD,
+        _ => false,
+    };
+    if to_get {
+        *req.method_mut() = Method::GET;
+        *req.body_mut() = None;
+        for name in [
+            header::CONTENT_TYPE,
+            header::CONTENT_LENGTH,
+            header::CONTENT_ENCODING,
+        ] {
+            req.headers_mut().remove(name);
+        }
+    }
+
+    // 跳到其他 host 时不再携带凭证
+    if !same_host(&url, req.url()) {
+        req.headers_mut().remove(header::AUTHORIZATION);
+        req.headers_mut().remove(header::COOKIE);
+    }
+    *req.url_mut() = url;
+    Ok(Some(req))
+}
+
+/// host 和端口都相同，跨 host 的跳转不能带上只属于原 host 的凭证和请求头
+pub fn same_host(a: &Url, b: &Url) -> bool {
+    a.host_str() == b.host_str() && a.port_or_known_default() == b.port_or_known_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Client;
+
+    fn location(url: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::LOCATION, url.parse().unwrap());
+        headers
+    }
+
+    fn post() -> Request {
+        Client::new()
+            .post("http://localhost/a/b")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, "Bearer x")
+            .body("{}")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn next_request_should_resolve_relative_location() {
+        let next = next_request(
+            post(),
+            StatusCode::TEMPORARY_REDIRECT,
+            &location("../c?d=1"),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(next.url().as_str(), "http://localhost/c?d=1");
+        assert_eq!(next.method(), Method::POST);
+        assert!(next.body().is_some());
+        assert!(next.headers().contains_key(header::AUTHORIZATION));
+    }
+
+    #[test]
+    fn next_request_should_switch_to_get_on_see_other() {
+        let next = next_request(post(), StatusCode::SEE_OTHER, &location("/done"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(next.method(), Method::GET);
+        assert!(next.body().is_none());
+        assert!(!next.headers().contains_key(header::CONTENT_TYPE));
+    }
+
+    #[test]
+    fn next_request_should_drop_credentials_across_hosts() {
+        let next = next_request(post(), StatusCode::FOUND, &location("https://example.com/"))
+            .unwrap()
+            .unwrap();
+        assert!(!next.headers().contains_key(header::AUTHORIZATION));
+    }
+
+    #[test]
+    fn next_request_should_ignore_non_redirects() {
+        assert!(next_request(post(), StatusCode::OK, &location("/x"))
+            .unwrap()
+            .is_none());
+        assert!(
+            next_request(post(), StatusCode::NOT_MODIFIED, &HeaderMap::new())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn chain_should_detect_loops_and_limit() {
+        let a = Client::new().get("http://localhost/a").build().unwrap();
+        let b = Client::new().get("http://localhost/b").build().unwrap();
+        let mut chain = Chain::new(&a);
+        chain.push(&b, 5).unwrap();
+        let err = chain.push(&a, 5).unwrap_err().to_string();
+        assert!(err.contains("Redirect loop detected"));
+        assert!(err.contains("1. GET http://localhost/a"));
+        assert!(err.contains("3. GET http://localhost/a"));
+
+        let mut chain = Chain::new(&a);
+        let err = chain.push(&b, 0).unwrap_err().to_string();
+        assert!(err.contains("Too many redirects (max 0)"));
+
+        // POST 之后 303 到同一个 URL 的 GET 不是循环，body 不同的 POST 也不是
+        let post = |body: &str| {
+            Client::new()
+                .post("http://localhost/a")
+                .body(body.to_string())
+                .build()
+                .unwrap()
+        };
+        let mut chain = Chain::new(&post("1"));
+        chain.push(&a, 5).unwrap();
+        chain.push(&post("2"), 5).unwrap();
+        assert!(chain.push(&post("1"), 5).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use reqwest::{
+    header::{self, HeaderMap},
+    Method, StatusCode,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 第一次重试前等待的时间，之后每次翻倍
+const BASE_DELAY: Duration = Duration::from_millis(500);
+/// 退避时间的上限，Retry-After 也不会超过它
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// 失败重试的配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct Retry {
+    pub retries: usize,
+    pub on: Vec<StatusCode>,
+    /// 非幂等的方法（POST、PATCH）默认不重试，避免重复提交
+    pub force: bool,
+}
+
+impl Retry {
+    /// 这个方法的请求是否允许重试
+    pub fn allows(&self, method: &Method) -> bool {
+        self.retries > 0 && (self.force || is_idempotent(method))
+    }
+
+    /// 第 attempt 次（从 0 开始）请求得到这个状态码后需要等待多久再重试，不需要重试时返回 None
+    pub fn delay_for_status(
+        &self,
+        status: StatusCode,
+        headers: &HeaderMap,
+        attempt: usize,
+    ) -> Option<Duration> {
+        if attempt >= self.retries || !self.on.contains(&status) {
+            return None;
+        }
+        let retry_after = headers
+            .get(header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after);
+        Some(retry_after.map_or_else(|| backoff(attempt), |d| d.min(MAX_DELAY)))
+    }
+
+    /// 超时和连接失败时同样按退避时间重试，其他错误直接返回
+    pub fn delay_for_error(&self, err: &anyhow::Error, attempt: usize) -> Option<Duration> {
+        let retryable = err
+            .chain()
+            .filter_map(|e| e.downcast_ref::<reqwest::Error>())
+            .any(|e| e.is_timeout() || e.is_connect());
+        (attempt < self.retries && retryable).then(|| backoff(attempt))
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE | Method::TRACE
+    )
+}
+
+/// 指数退避加上随机抖动：第 n 次重试等待 base * 2^n 再加上 [0, base * 2^n) 之间的随机值
+fn backoff(attempt: usize) -> Duration {
+    let delay = BASE_DELAY
+        .saturating_mul(1 << attempt.min(16))
+        .min(MAX_DELAY);
+    // 只是为了让多个客户端错开重试时间，用当前时间的纳秒部分当随机数就够了
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    let jitter = delay.mul_f64(nanos as f64 / 1_000_000_000.0);
+    (delay + jitter).min(MAX_DELAY)
+}
+
+/// Retry-After 可以是秒数，也可以是一个 HTTP 日期
+fn parse_retry_after(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    match s.parse::<u64>() {
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        Err(_) => {
+            let at = httpdate::parse_http_date(s).ok()?;
+            Some(at.duration_since(SystemTime::now()).unwrap_or_default())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retry(force: bool) -> Retry {
+        Retry {
+            retries: 2,
+            on: vec![StatusCode::SERVICE_UNAVAILABLE],
+            force,
+        }
+    }
+
+    #[test]
+    fn allows_should_only_retry_idempotent_methods() {
+        assert!(retry(false).allows(&Method::GET));
+        assert!(retry(false).allows(&Method::PUT));
+        assert!(!retry(false).allows(&Method::POST));
+        assert!(retry(true).allows(&Method::POST));
+        let none = Retry {
+            retries: 0,
+            ..retry(true)
+        };
+        assert!(!none.allows(&Method::GET));
+    }
+
+    #[test]
+    fn delay_for_status_should_honor_retry_after() {
+        let retry = retry(false);
+        let mut headers = HeaderMap::new();
+        let delay = retry
+            .delay_for_status(StatusCode::SERVICE_UNAVAILABLE, &headers, 0)
+            .unwrap();
+        assert!(delay >= BASE_DELAY && delay < BASE_DELAY * 2);
+        let delay = retry
+            .delay_for_status(StatusCode::SERVICE_UNAVAILABLE, &headers, 1)
+            .unwrap();
+        assert!(delay >= BASE_DELAY * 2 && delay < BASE_DELAY * 4);
+
+        headers.insert(header::RETRY_AFTER, "3".parse().unwrap());
+        assert_eq!(
+            retry.delay_for_status(StatusCode::SERVICE_UNAVAILABLE, &headers, 0),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            retry.delay_for_status(StatusCode::SERVICE_UNAVAILABLE, &headers, 2),
+            None
+        );
+        assert_eq!(
+            retry.delay_for_status(StatusCode::BAD_GATEWAY, &headers, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_retry_after_should_support_http_date() {
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        let later = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        let delay = parse_retry_after(&later).unwrap();
+        assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
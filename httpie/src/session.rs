@@ -0,0 +1,388 @@
+use crate::auth::Auth;
+use anyhow::{anyhow, Context, Result};
+use reqwest::{
+    cookie::CookieStore,
+    header::{self, HeaderMap, HeaderValue},
+    Url,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// 这些请求头和具体的请求相关，不应该保存到 session 中
+const IGNORED_HEADERS: [header::HeaderName; 3] =
+    [header::CONTENT_TYPE, header::CONTENT_LENGTH, header::COOKIE];
+
+/// 命名 session：保存 cookies、默认请求头以及认证信息，跨多次调用复用
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(skip)]
+    read_only: bool,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    cookies: Vec<Cookie>,
+    #[serde(default)]
+    auth: Option<Auth>,
+    #[serde(skip)]
+    jar: Arc<SessionCookies>,
+}
+
+impl Session {
+    /// 加载 session，name 中包含路径分隔符时直接当作文件路径，否则按 host 存放在配置目录下
+    pub fn load(name: &str, url: &str, read_only: bool) -> Result<Self> {
+        let path = match name.contains(std::path::MAIN_SEPARATOR) || name.ends_with(".json") {
+            true => PathBuf::from(name),
+            false => sessions_dir()?
+                .join(host_dir(url)?)
+                .join(format!("{}.json", name)),
+        };
+
+        let mut session: Session = match path.exists() {
+            true => {
+                let content = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read session {}", path.display()))?;
+                serde_json::from_str(&content)
+                    .with_context(|| format!("Invalid session file {}", path.display()))?
+            }
+            false => Session::default(),
+        };
+        // 过期的 cookie 直接丢掉
+        let now = unix_time(SystemTime::now());
+        session.cookies.retain(|c| !c.is_expired(now));
+        session.jar = Arc::new(SessionCookies(RwLock::new(session.cookies.clone())));
+        session.path = path;
+        session.read_only = read_only;
+        Ok(session)
+    }
+
+    /// 供 reqwest Client 使用的 cookie store，请求过程中服务器设置的 cookie 都会记录在这里
+    pub fn cookie_store(&self) -> Arc<SessionCookies> {
+        self.jar.clone()
+    }
+
+    /// session 中保存的默认请求头
+    pub fn headers(&self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        for (k, v) in &self.headers {
+            headers.insert(header::HeaderName::from_bytes(k.as_bytes())?, v.parse()?);
+        }
+        Ok(headers)
+    }
+
+    pub fn auth(&self) -> Option<&Auth> {
+        self.auth.as_ref()
+    }
+
+    /// 用本次请求的请求头和认证信息更新 session，并把 cookie store 中的 cookie 写回文件
+    pub fn save(mut self, headers: &HeaderMap, auth: Option<&Auth>) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        for (k, v) in headers {
+            if IGNORED_HEADERS.contains(k) {
+                continue;
+            }
+            self.headers
+                .insert(k.to_string(), String::from_utf8_lossy(v.as_bytes()).into());
+        }
+        if let Some(auth) = auth {
+            self.auth = Some(auth.clone());
+        }
+        self.cookies = self.jar.0.read().map_err(|e| anyhow!("{}", e))?.clone();
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self)?)
+            .with_context(|| format!("Failed to write session {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+/// session 中保存的一个 cookie。domain 和 path 决定发送给哪些请求，expires 是过期时间的
+/// Unix 时间戳，没有时是会话 cookie，和 httpie 一样也会一直保存在 session 中
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    domain: String,
+    /// 没有 Domain 属性时只发送给设置它的 host，不包括子域名
+    #[serde(default)]
+    host_only: bool,
+    #[serde(default = "default_path")]
+    path: String,
+    #[serde(default)]
+    secure: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires: Option<u64>,
+}
+
+fn default_path() -> String {
+    "/".into()
+}
+
+impl Cookie {
+    /// 按 RFC 6265 解析 Set-Cookie，Domain 和请求的 host 不匹配时忽略这个 cookie
+    fn parse(header: &str, url: &Url, now: u64) -> Option<Self> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let mut attrs = header.split(';').map(str::trim);
+        let (name, value) = attrs.next()?.split_once('=')?;
+        let mut cookie = Cookie {
+            name: name.trim().to_string(),
+            value: value.trim().to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_cookie_path(url.path()),
+            secure: false,
+            expires: None,
+        };
+        let mut max_age = None;
+        for attr in attrs {
+            let (k, v) = attr.split_once('=').unwrap_or((attr, ""));
+            let v = v.trim();
+            match k.trim().to_ascii_lowercase().as_str() {
+                "domain" if !v.is_empty() => {
+                    let domain = v.trim_start_matches('.').to_ascii_lowercase();
+                    if !domain_match(&host, &domain) {
+                        return None;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if v.starts_with('/') => cookie.path = v.to_string(),
+                "secure" => cookie.secure = true,
+                "max-age" => max_age = v.parse::<i64>().ok(),
+                "expires" => {
+                    if let Ok(time) = httpdate::parse_http_date(v) {
+                        cookie.expires = Some(unix_time(time));
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Max-Age 优先于 Expires，小于等于 0 表示立即过期
+        if let Some(age) = max_age {
+            cookie.expires = Some(match age {
+                ..=0 => 0,
+                age => now.saturating_add(age as u64),
+            });
+        }
+        (!cookie.name.is_empty()).then_some(cookie)
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        let domain = match self.host_only {
+            true => host == self.domain,
+            false => domain_match(&host, &self.domain),
+        };
+        domain && path_match(url.path(), &self.path) && (!self.secure || url.scheme() == "https")
+    }
+
+    fn same_key(&self, other: &Cookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+
+/// host 等于 domain，或者是 domain 的子域名
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.parse::<std::net::IpAddr>().is_err())
+}
+
+/// 请求路径等于 cookie 的 path，或者以 path 为前缀且前缀之后是新的一段
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/')))
+}
+
+/// 没有 Path 属性时取请求路径中最后一个 `/` 之前的部分
+fn default_cookie_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".into(),
+        Some(i) => path[..i].into(),
+    }
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// session 的 cookie jar，按 domain 和 path 匹配请求，跟随重定向到其他 host 时不会把 cookie 带过去
+#[derive(Debug, Default)]
+pub struct SessionCookies(RwLock<Vec<Cookie>>);
+
+impl CookieStore for SessionCookies {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let Ok(mut cookies) = self.0.write() else {
+            return;
+        };
+        let now = unix_time(SystemTime::now());
+        for value in cookie_headers {
+            let Some(cookie) = value.to_str().ok().and_then(|v| Cookie::parse(v, url, now)) else {
+                continue;
+            };
+            cookies.retain(|c| !c.same_key(&cookie));
+            if !cookie.is_expired(now) {
+                cookies.push(cookie);
+            }
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let cookies = self.0.read().ok()?;
+        let now = unix_time(SystemTime::now());
+        let mut matched: Vec<_> = cookies
+            .iter()
+            .filter(|c| !c.is_expired(now) && c.matches(url))
+            .collect();
+        if matched.is_empty() {
+            return None;
+        }
+        // path 更长的 cookie 排在前面
+        matched.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+        let cookie = matched
+            .iter()
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        HeaderValue::from_str(&cookie).ok()
+    }
+}
+
+fn sessions_dir() -> Result<PathBuf> {
+    let dir = dirs::config_dir().ok_or_else(|| anyhow!("Cannot find config directory"))?;
+    Ok(dir.join("httpie-rs").join("sessions"))
+}
+
+/// 和 httpie 一样用 host_port 作为目录名，不同 host 的同名 session 互不影响
+fn host_dir(url: &str) -> Result<PathBuf> {
+    let url: Url = url.parse()?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow!("Missing host in {}", url))?;
+    Ok(PathBuf::from(match url.port() {
+        Some(port) => format!("{}_{}", host, port),
+        None => host.to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::AuthType;
+
+    #[test]
+    fn cookie_store_should_track_set_cookie() {
+        let jar = SessionCookies::default();
+        let url: Url = "http://localhost".parse().unwrap();
+        let set = [
+            HeaderValue::from_static("sid=abc; Path=/; HttpOnly"),
+            HeaderValue::from_static("theme=dark"),
+        ];
+        jar.set_cookies(&mut set.iter(), &url);
+        assert_eq!(jar.cookies(&url).unwrap(), "sid=abc; theme=dark");
+
+        let delete = [HeaderValue::from_static("theme=; Max-Age=0")];
+        jar.set_cookies(&mut delete.iter(), &url);
+        assert_eq!(jar.cookies(&url).unwrap(), "sid=abc");
+
+        let expired = [HeaderValue::from_static(
+            "sid=abc; Expires=Thu, 01 Jan 1970 00:00:01 GMT",
+        )];
+        jar.set_cookies(&mut expired.iter(), &url);
+        assert_eq!(jar.cookies(&url), None);
+    }
+
+    #[test]
+    fn cookie_store_should_match_domain_and_path() {
+        let jar = SessionCookies::default();
+        let url: Url = "http://api.example.com/v1/users".parse().unwrap();
+        let set = [
+            HeaderValue::from_static("host=1"),
+            HeaderValue::from_static("shared=2; Domain=.example.com; Path=/"),
+            HeaderValue::from_static("admin=3; Path=/admin"),
+            HeaderValue::from_static("secure=4; Secure"),
+            HeaderValue::from_static("evil=5; Domain=other.com"),
+        ];
+        jar.set_cookies(&mut set.iter(), &url);
+
+        let cookies = |url: &str| jar.cookies(&url.parse().unwrap());
+        assert_eq!(
+            cookies("http://api.example.com/v1/x").unwrap(),
+            "host=1; shared=2"
+        );
+        assert_eq!(
+            cookies("https://api.example.com/v1/").unwrap(),
+            "host=1; secure=4; shared=2"
+        );
+        assert_eq!(cookies("http://api.example.com/v2").unwrap(), "shared=2");
+        assert_eq!(cookies("http://www.example.com/").unwrap(), "shared=2");
+        assert_eq!(
+            cookies("http://api.example.com/admin/x").unwrap(),
+            "admin=3; shared=2"
+        );
+        assert_eq!(
+            cookies("http://api.example.com/administrator").unwrap(),
+            "shared=2"
+        );
+        assert_eq!(cookies("http://other.com/"), None);
+        assert_eq!(cookies("http://evil.com/"), None);
+    }
+
+    #[test]
+    fn host_dir_should_include_port() {
+        assert_eq!(
+            host_dir("http://localhost:3000/a").unwrap(),
+            PathBuf::from("localhost_3000")
+        );
+        assert_eq!(
+            host_dir("https://example.com").unwrap(),
+            PathBuf::from("example.com")
+        );
+    }
+
+    #[test]
+    fn session_should_round_trip() {
+        let path = std::env::temp_dir().join("httpie-session-test.json");
+        let _ = fs::remove_file(&path);
+        let name = path.to_str().unwrap();
+
+        let session = Session::load(name, "http://localhost", false).unwrap();
+        let url: Url = "http://localhost".parse().unwrap();
+        let set = [HeaderValue::from_static("sid=abc")];
+        session.cookie_store().set_cookies(&mut set.iter(), &url);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "secret".parse().unwrap());
+        headers.insert(header::CONTENT_TYPE, "text/plain".parse().unwrap());
+        let auth = Auth::new(AuthType::Bearer, "token").unwrap();
+        session.save(&headers, Some(&auth)).unwrap();
+
+        let session = Session::load(name, "http://localhost", true).unwrap();
+        assert_eq!(session.cookie_store().cookies(&url).unwrap(), "sid=abc");
+        let headers = session.headers().unwrap();
+        assert_eq!(headers["x-api-key"], "secret");
+        assert!(!headers.contains_key(header::CONTENT_TYPE));
+        assert_eq!(session.auth(), Some(&auth));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+/// 把 httpie 风格的 URL 简写展开成完整的 URL：
+///
+/// - `:3000/api` => `http://localhost:3000/api`，`:/api` => `http://localhost/api`
+/// - `example.com/path` => `<default_scheme>://example.com/path`
+/// - 已经带 scheme 的 URL 保持不变
+pub fn expand_url(s: &str, default_scheme: &str) -> String {
+    if has_scheme(s) {
+        return s.to_string();
+    }
+    let rest = match s.strip_prefix(':') {
+        // `:3000/api` 和 `:/api` 都是 localhost 的简写
+        Some(rest) => match rest.strip_prefix('/') {
+            Some(path) => format!("localhost/{}", path),
+            None => format!("localhost:{}", rest)
+                .trim_end_matches(':')
+                .to_string(),
+        },
+        None => s.to_string(),
+    };
+    format!("{}://{}", default_scheme, rest)
+}
+
+/// 和 RFC 3986 一样，scheme 以字母开头，由字母、数字、`+`、`-`、`.` 组成
+fn has_scheme(s: &str) -> bool {
+    let Some((scheme, _)) = s.split_once("://") else {
+        return false;
+    };
+    let mut chars = scheme.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_url_should_handle_localhost_shorthand() {
+        assert_eq!(expand_url(":3000/api", "http"), "http://localhost:3000/api");
+        assert_eq!(expand_url(":3000", "http"), "http://localhost:3000");
+        assert_eq!(expand_url(":/api?a=1", "http"), "http://localhost/api?a=1");
+        assert_eq!(expand_url(":", "https"), "https://localhost");
+    }
+
+    #[test]
+    fn expand_url_should_add_default_scheme() {
+        assert_eq!(
+            expand_url("example.com/path", "http"),
+            "http://example.com/path"
+        );
+        assert_eq!(
+            expand_url("localhost:8080", "https"),
+            "https://localhost:8080"
+        );
+        assert_eq!(
+            expand_url("https://example.com", "http"),
+            "https://example.com"
+        );
+        assert_eq!(expand_url("ws+unix://socket", "http"), "ws+unix://socket");
+    }
+}
@@ -0,0 +1,148 @@
+/// 一个 server-sent event，字段含义见 https://html.spec.whatwg.org/multipage/server-sent-events.html
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Event {
+    pub event: Option<String>,
+    pub id: Option<String>,
+    pub retry: Option<u64>,
+    pub data: String,
+}
+
+/// 增量解析 text/event-stream，chunk 可以在任意位置被截断
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buf: Vec<u8>,
+    event: Event,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// 喂入一段数据，返回其中已经完整的事件
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Event> {
+        self.buf.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buf.iter().position(|&b| b == b'\n' || b == b'\r') {
+            // \r\n 当作一个换行处理，如果 \r 恰好是最后一个字节，需要等下一个 chunk 才能判断
+            let skip = match (self.buf[pos], self.buf.get(pos + 1)) {
+                (b'\r', Some(b'\n')) => 2,
+                (b'\r', None) => break,
+                _ => 1,
+            };
+            let line: Vec<u8> = self.buf.drain(..pos + skip).take(pos).collect();
+            if let Some(event) = self.process_line(&String::from_utf8_lossy(&line)) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// 流结束时调用，返回还没遇到空行的最后一个事件
+    pub fn finish(&mut self) -> Option<Event> {
+        // 末尾单独的 \r 在 feed 中还在等待后面的 \n，流结束后它就是一个换行
+        let mut rest = std::mem::take(&mut self.buf);
+        let line_end = rest.last() == Some(&b'\r');
+        if line_end {
+            rest.pop();
+        }
+        if !rest.is_empty() || line_end {
+            if let Some(event) = self.process_line(&String::from_utf8_lossy(&rest)) {
+                return Some(event);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<Event> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        // 冒号开头的是注释，常被服务器用作心跳
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" => self.event.id = Some(value.to_string()),
+            "retry" => self.event.retry = value.parse().ok().or(self.event.retry),
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<Event> {
+        let mut event = std::mem::take(&mut self.event);
+        if self.data.is_empty() {
+            return None;
+        }
+        event.data = std::mem::take(&mut self.data).join("\n");
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sse_parser_should_parse_events() {
+        let mut parser = SseParser::default();
+        let events = parser.feed(
+            b": heartbeat\nevent: update\nid: 1\ndata: {\"a\":1}\n\ndata: line1\ndata: line2\n\n",
+        );
+        assert_eq!(
+            events,
+            vec![
+                Event {
+                    event: Some("update".into()),
+                    id: Some("1".into()),
+                    retry: None,
+                    data: "{\"a\":1}".into(),
+                },
+                Event {
+                    data: "line1\nline2".into(),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn sse_parser_should_handle_split_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b"da").is_empty());
+        assert!(parser.feed(b"ta: hel").is_empty());
+        assert!(parser.feed(b"lo\r").is_empty());
+        assert!(parser.feed(b"\n\r").is_empty());
+        let events = parser.feed(b"\nretry: 3000\ndata:x");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "hello");
+        assert_eq!(
+            parser.finish(),
+            Some(Event {
+                retry: Some(3000),
+                data: "x".into(),
+                ..Default::default()
+            })
+        );
+
+        // 以单独的 \r 结尾的流
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b"data: a\r").is_empty());
+        assert_eq!(parser.finish().unwrap().data, "a");
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b"data: b\r\r").is_empty());
+        assert_eq!(parser.finish().unwrap().data, "b");
+        assert_eq!(parser.finish(), None);
+    }
+
+    #[test]
+    fn sse_parser_should_skip_events_without_data() {
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b"event: ping\n\n").is_empty());
+        assert_eq!(parser.finish(), None);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::printer::Printer;
+use anyhow::{anyhow, Result};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::Request;
+use std::io::BufRead;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::mpsc,
+};
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, handshake::client, Message},
+    WebSocketStream,
+};
+
+/// ws:// 和 wss:// 先换成 http:// 和 https://，这样请求头、basic / bearer 认证以及
+/// session 中的请求头都可以复用构造 HTTP 请求的逻辑，握手时再换回来
+pub fn http_url(url: &str) -> Result<String> {
+    let (scheme, rest) = url
+        .split_once("://")
+        .ok_or_else(|| anyhow!("Invalid WebSocket URL {}", url))?;
+    let scheme = match scheme.to_ascii_lowercase().as_str() {
+        "ws" | "http" => "http",
+        "wss" | "https" => "https",
+        _ => {
+            return Err(anyhow!(
+                "Invalid WebSocket URL {}, expect ws:// or wss://",
+                url
+            ))
+        }
+    };
+    Ok(format!("{}://{}", scheme, rest))
+}
+
+/// 把构造好的 HTTP 请求转换成 WebSocket 握手请求。握手需要的请求头也会写回 req，
+/// 这样 --verbose 输出的就是实际发送的请求
+pub fn handshake(req: &mut Request) -> Result<client::Request> {
+    if req.body().is_some() {
+        return Err(anyhow!(
+            "WebSocket messages are read from stdin, data items cannot be used with ws"
+        ));
+    }
+    let mut url = req.url().clone();
+    let scheme = match url.scheme() {
+        "https" => "wss",
+        _ => "ws",
+    };
+    url.set_scheme(scheme)
+        .map_err(|_| anyhow!("Invalid WebSocket URL {}", url))?;
+
+    let mut handshake = url.as_str().into_client_request()?;
+    handshake.headers_mut().extend(req.headers().clone());
+    *req.headers_mut() = handshake.headers().clone();
+    Ok(handshake)
+}
+
+/// 在单独的线程中逐行读取 stdin。tokio 的 stdin 在运行时退出时会一直等到读完一行，
+/// 交互使用时会卡住，所以这里不用它
+pub fn stdin_lines() -> mpsc::UnboundedReceiver<String> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// 把 input 中的每一行作为文本消息发送，同时输出收到的消息。input 结束后发送 Close，
+/// 并继续输出服务器在关闭连接前发来的消息
+pub async fn chat<S>(
+    printer: &Printer,
+    socket: WebSocketStream<S>,
+    mut input: mpsc::UnboundedReceiver<String>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (mut sink, mut stream) = socket.split();
+    let mut input_open = true;
+    loop {
+        tokio::select! {
+            line = input.recv(), if input_open => match line {
+                Some(line) => sink.send(Message::Text(line)).await?,
+                None => {
+                    input_open = false;
+                    sink.send(Message::Close(None)).await?;
+                }
+            },
+            msg = stream.next() => match msg {
+                Some(msg) => match msg? {
+                    Message::Text(text) => printer.print_message(&text),
+                    Message::Binary(data) => printer.print_binary_message(data.len()),
+                    Message::Close(Some(frame)) => {
+                        eprintln!("Connection closed: {} {}", frame.code, frame.reason)
+                    }
+                    _ => {}
+                },
+                None => break,
+            },
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::printer::Pretty;
+    use reqwest::{header, Client};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn handshake_should_keep_headers_and_query() {
+        let url = http_url("wss://localhost/chat?room=1").unwrap();
+        assert_eq!(url, "https://localhost/chat?room=1");
+        assert!(http_url("ftp://localhost/").is_err());
+
+        let mut req = Client::new()
+            .get(&url)
+            .header(header::AUTHORIZATION, "Bearer abc")
+            .build()
+            .unwrap();
+        let handshake = handshake(&mut req).unwrap();
+        assert_eq!(handshake.uri(), "wss://localhost/chat?room=1");
+        assert_eq!(handshake.headers()[header::AUTHORIZATION], "Bearer abc");
+        assert_eq!(handshake.headers()[header::UPGRADE], "websocket");
+        assert_eq!(req.headers()[header::UPGRADE], "websocket");
+
+        let mut req = Client::new().post(&url).body("x").build().unwrap();
+        assert!(super::handshake(&mut req).is_err());
+    }
+
+    #[tokio::test]
+    async fn chat_should_send_lines_and_close() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        // 回显收到的文本消息，并记录下来
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let mut received = Vec::new();
+            while let Some(Ok(msg)) = socket.next().await {
+                if let Message::Text(text) = msg {
+                    received.push(text.clone());
+                    socket.send(Message::Text(text)).await.unwrap();
+                }
+            }
+            received
+        });
+
+        let mut req = Client::new().get(&url).build().unwrap();
+        let (socket, resp) = tokio_tungstenite::connect_async(handshake(&mut req).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 101);
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        tx.send("hello".to_string()).unwrap();
+        tx.send("{\"a\":1}".to_string()).unwrap();
+        drop(tx);
+        let printer = Printer::new(None, Some(Pretty::None), None);
+        tokio::time::timeout(Duration::from_secs(5), chat(&printer, socket, rx))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(server.await.unwrap(), vec!["hello", "{\"a\":1}"]);
+    }
+}